@@ -1,104 +1,149 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Result, bail};
+use clap::{Parser, ValueEnum};
 use itertools::Itertools;
-use lammps_util_rust::{DumpFile, DumpSnapshot, SymBox, XYZ};
-// use rayon::prelude::*;
-use std::{array, f64, iter, ops::Deref, path::PathBuf};
+use lammps_util_rust::{DumpFile, DumpSnapshot, NeighborBackend, NeighborList, PairCutoffs};
+use log::info;
+use rayon::prelude::*;
+use std::{f64::consts::PI, iter, path::PathBuf};
 
+/// Bond-angle distribution of i-k-j triplets around the central atoms k
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
     dump_file: PathBuf,
 
-    #[arg(short, long)]
-    timestep: Option<u64>,
+    /// Timesteps to average over, all by default
+    #[arg(short, long, value_delimiter = ',')]
+    timesteps: Vec<u64>,
 
     #[arg(short, long)]
     n_bins: usize,
 
+    /// Cutoffs (A) like `1-1:2.6,1-2:2.1,2-2:1.9`, a bare number applies to
+    /// the pairs not listed
     #[arg(short, long)]
-    type_i: usize,
+    cutoffs: PairCutoffs,
 
-    #[arg(short, long)]
-    type_j: usize,
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
 
-    #[arg(short, long)]
-    type_k: usize,
+    /// Type of the first neighbour, any by default
+    #[arg(short = 'i', long)]
+    type_i: Option<usize>,
 
-    #[arg(short, long)]
-    cutoff_i: f64,
+    /// Type of the second neighbour, any by default
+    #[arg(short = 'j', long)]
+    type_j: Option<usize>,
 
-    #[arg(short, long)]
-    cutoff_j: f64,
+    /// Type of the central atom, any by default
+    #[arg(short = 'k', long)]
+    type_k: Option<usize>,
+
+    /// How the histogram is normalised
+    #[arg(short = 'N', long, default_value = "probability")]
+    normalize: Normalize,
 }
 
-fn get_bins(n: usize) -> Vec<(f64, f64)> {
-    let delta = 180.0 / n as f64;
-    (0..n)
-        .map(|i| i as f64)
-        .map(|i| (i * delta, (i + 1.0) * delta))
-        .collect()
+#[derive(Clone, Copy, ValueEnum)]
+enum Normalize {
+    /// Triplets per central atom in every bin
+    Count,
+
+    /// Probability density over the angle (1/deg)
+    Probability,
+
+    /// Probability density over sin(theta), 1 for random bond directions
+    Sine,
 }
 
-fn get_cos((atom_i, atom_j, atom_k): (&XYZ, &XYZ, &XYZ)) -> f64 {
-    let (atom_i, atom_j, atom_k) = (*atom_i.clone(), *atom_j.clone(), *atom_k.clone());
-    let r1 = atom_i - atom_k;
-    let r2 = atom_j - atom_k;
-    0.0
+fn is_type(t: usize, wanted: Option<usize>) -> bool {
+    wanted.is_none_or(|wanted| t == wanted)
 }
 
-fn get_adf(
-    type_i: usize,
-    type_j: usize,
-    type_k: usize,
-    cutoff_i: f32,
-    cutoff_j: f32,
-    n: usize,
-    dump: &DumpSnapshot,
-) -> Vec<(f64, f64)> {
-    let bins = get_bins(n);
-    let mut coords = dump.get_coordinates();
-    let d_types = dump.get_property("type");
-    let kdtree = kd_tree::KdTree::build_by_ordered_float(coords);
-    kdtree
-        .items()
+/// Angle histogram of one snapshot and the number of central atoms
+fn get_histogram(snapshot: &DumpSnapshot, cli: &Cli) -> (Vec<usize>, usize) {
+    let types = snapshot
+        .get_property("type")
         .iter()
-        .filter(|atom| d_types[atom.index()] as usize == type_k)
-        .flat_map(|atom_k| {
-            let i_neigh = kdtree.within_radius(atom_k, cutoff_i);
-            let mut j_neigh = kdtree.within_radius(atom_k, cutoff_j);
-            j_neigh.retain(|atom_j| {
-                d_types[atom_j.index()] as usize == type_j && atom_j.index() != atom_k.index()
-            });
-            i_neigh
-                .into_iter()
-                .filter(|atom_i| {
-                    d_types[atom_i.index()] as usize == type_i && atom_i.index() != atom_k.index()
-                })
-                .flat_map(move |atom_i| j_neigh.iter().map(move |atom_j| (atom_i, *atom_j, atom_k)))
-        })
-        .map(get_cos);
-    Vec::new()
+        .map(|&t| t as usize)
+        .collect::<Vec<_>>();
+    let neighbors = NeighborList::<f64>::new_with_cutoffs(snapshot, &cli.cutoffs, cli.backend);
+    let centers = (0..snapshot.atoms_count)
+        .filter(|&k| is_type(types[k], cli.type_k))
+        .collect::<Vec<_>>();
+    let width = 180.0 / cli.n_bins as f64;
+    let histogram = centers
+        .par_iter()
+        .fold(
+            || vec![0; cli.n_bins],
+            |mut histogram, &k| {
+                for (n1, n2) in neighbors.neighbors(k).iter().tuple_combinations() {
+                    let (t1, t2) = (types[n1.index], types[n2.index]);
+                    let matches = (is_type(t1, cli.type_i) && is_type(t2, cli.type_j))
+                        || (is_type(t2, cli.type_i) && is_type(t1, cli.type_j));
+                    if !matches {
+                        continue;
+                    }
+                    let cos = n1.delta.dot(n2.delta) / (n1.distance * n2.distance);
+                    let angle = cos.clamp(-1.0, 1.0).acos().to_degrees();
+                    let bin = ((angle / width) as usize).min(cli.n_bins - 1);
+                    histogram[bin] += 1;
+                }
+                histogram
+            },
+        )
+        .reduce(
+            || vec![0; cli.n_bins],
+            |mut a, b| {
+                iter::zip(&mut a, b).for_each(|(a, b)| *a += b);
+                a
+            },
+        );
+    (histogram, centers.len())
 }
 
 fn main() -> Result<()> {
     env_logger::init();
     let cli = Cli::parse();
-    let dump_path = cli.dump_file;
-    let timesteps = match cli.timestep {
-        Some(timestep) => vec![timestep],
-        _ => vec![],
-    };
-    let dump = DumpFile::read(dump_path.as_path(), &timesteps)?;
-    let snapshot = dump.get_snapshots()[0];
-    let adf = get_adf(
-        cli.type_i,
-        cli.type_j,
-        cli.type_k,
-        cli.cutoff_i as f32,
-        cli.cutoff_j as f32,
-        cli.n_bins,
-        snapshot,
-    );
+    if cli.n_bins == 0 {
+        bail!("Expected at least one bin");
+    }
+    let dump = DumpFile::read(&cli.dump_file, &cli.timesteps)?;
+    let mut histogram = vec![0; cli.n_bins];
+    let mut centers = 0;
+    for snapshot in dump.get_snapshots() {
+        let (counts, n) = get_histogram(snapshot, &cli);
+        iter::zip(&mut histogram, counts).for_each(|(a, b)| *a += b);
+        centers += n;
+    }
+    let triplets = histogram.iter().sum::<usize>();
+    info!("{triplets} triplets around {centers} central atoms");
+    if triplets == 0 {
+        bail!("No triplets found");
+    }
+    let width = 180.0 / cli.n_bins as f64;
+    let table = histogram
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| {
+            let angle = (i as f64 + 0.5) * width;
+            let value = match cli.normalize {
+                Normalize::Count => count as f64 / centers as f64,
+                Normalize::Probability => count as f64 / (triplets as f64 * width),
+                Normalize::Sine => {
+                    let (lo, hi) = (
+                        (angle - width / 2.0).to_radians(),
+                        (angle + width / 2.0).to_radians(),
+                    );
+                    // integral of sin over the bin, exact also for the end bins
+                    let sine = (lo.cos() - hi.cos()) / (hi - lo);
+                    count as f64 / (triplets as f64 * width * sine) * 360.0 / PI
+                }
+            };
+            [angle, value].map(|x| format!("{x:10.4}")).join("\t")
+        })
+        .join("\n");
+    println!("# angle adf\n{table}");
     Ok(())
 }
@@ -0,0 +1,64 @@
+//! Compares neighbour search backends on diamond Si supercells of growing
+//! size, run with `cargo bench --bench neighbors`.
+
+use lammps_util_rust::{
+    geomutil_util::BoundingBox3, CellList, KdTreeSearch, NeighborList, NeighborSearch, SymBox,
+    Vector3,
+};
+use std::time::{Duration, Instant};
+
+const LATTICE: f64 = 5.43;
+const CUTOFF: f64 = 2.6;
+const REPEATS: u32 = 3;
+
+const BASIS: [[f64; 3]; 8] = [
+    [0.0, 0.0, 0.0],
+    [0.0, 0.5, 0.5],
+    [0.5, 0.0, 0.5],
+    [0.5, 0.5, 0.0],
+    [0.25, 0.25, 0.25],
+    [0.25, 0.75, 0.75],
+    [0.75, 0.25, 0.75],
+    [0.75, 0.75, 0.25],
+];
+
+fn diamond(n: usize) -> (Vec<Vector3<f64>>, SymBox) {
+    let positions = (0..n)
+        .flat_map(|i| (0..n).flat_map(move |j| (0..n).map(move |k| [i, j, k])))
+        .flat_map(|cell| {
+            BASIS.iter().map(move |b| {
+                Vector3::from(std::array::from_fn(|d| (cell[d] as f64 + b[d]) * LATTICE))
+            })
+        })
+        .collect();
+    let size = (n as f64 * LATTICE) as f32;
+    let sym_box = SymBox {
+        boundaries: "pp pp pp".to_string(),
+        bbox: BoundingBox3::new([0.0; 3].into(), [size; 3].into()),
+    };
+    (positions, sym_box)
+}
+
+fn measure<S: NeighborSearch<f64>>(positions: &[Vector3<f64>], sym_box: &SymBox) -> Duration {
+    let start = Instant::now();
+    for _ in 0..REPEATS {
+        let neighbors = NeighborList::from_positions_with_search::<S>(positions, sym_box, CUTOFF);
+        assert_eq!(neighbors.neighbors(0).len(), 4);
+    }
+    start.elapsed() / REPEATS
+}
+
+fn main() {
+    println!("# atoms kd-tree(ms) cell-list(ms)");
+    for n in [4, 8, 16, 24] {
+        let (positions, sym_box) = diamond(n);
+        let kd_tree = measure::<KdTreeSearch>(&positions, &sym_box);
+        let cell_list = measure::<CellList>(&positions, &sym_box);
+        println!(
+            "{}\t{:10.2}\t{:10.2}",
+            positions.len(),
+            kd_tree.as_secs_f64() * 1e3,
+            cell_list.as_secs_f64() * 1e3
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{Result, bail};
+use clap::{Args, Parser, Subcommand};
+use itertools::Itertools;
+use lammps_util_rust::{Axis, BinAccumulator, Binning, DumpFile};
+use log::info;
+use std::path::PathBuf;
+
+/// Averages of per-atom columns in spatial bins over the snapshots of a dump
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    dump_file: PathBuf,
+
+    /// Columns to average, comma separated
+    #[arg(short, long, value_delimiter = ',')]
+    keys: Vec<String>,
+
+    /// Timesteps to use, all by default
+    #[arg(short, long, value_delimiter = ',')]
+    timesteps: Vec<u64>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Slabs, columns or boxes along the given axes
+    Cartesian(CartesianCMD),
+
+    /// Cylindrical shells around an axis
+    Cylindrical(CylindricalCMD),
+
+    /// Spherical shells around a point
+    Spherical(SphericalCMD),
+}
+
+#[derive(Args)]
+struct CartesianCMD {
+    /// Axes with the bin widths (A) like `z:2.0,x:5.0`
+    #[arg(short, long, value_delimiter = ',', required = true)]
+    axes: Vec<String>,
+}
+
+#[derive(Args)]
+struct CylindricalCMD {
+    /// Axis of the cylinder
+    #[arg(short, long, default_value = "z")]
+    axis: Axis,
+
+    /// Position of the axis in the normal plane
+    #[arg(short, long, value_delimiter = ',', default_value = "0,0")]
+    center: Vec<f64>,
+
+    /// Radial bin width (A)
+    #[arg(short, long)]
+    width: f64,
+
+    /// Bin width along the axis (A), the whole box by default
+    #[arg(long)]
+    axial_width: Option<f64>,
+}
+
+#[derive(Args)]
+struct SphericalCMD {
+    /// Centre of the shells
+    #[arg(short, long, value_delimiter = ',', default_value = "0,0,0")]
+    center: Vec<f64>,
+
+    /// Radial bin width (A)
+    #[arg(short, long)]
+    width: f64,
+}
+
+fn check_width(width: f64) -> Result<()> {
+    if width.is_nan() || width <= 0.0 {
+        bail!("Bin width must be positive, got {width}");
+    }
+    Ok(())
+}
+
+fn get_binning(command: &Commands) -> Result<Binning> {
+    let binning = match command {
+        Commands::Cartesian(args) => Binning::Cartesian(
+            args.axes
+                .iter()
+                .map(|axis| {
+                    let Some((axis, width)) = axis.split_once(':') else {
+                        bail!("Expected axis:width, got {axis}");
+                    };
+                    let width = width.parse::<f64>()?;
+                    check_width(width)?;
+                    Ok((axis.parse().map_err(anyhow::Error::msg)?, width))
+                })
+                .collect::<Result<_>>()?,
+        ),
+        Commands::Cylindrical(args) => {
+            let Ok(center) = <[f64; 2]>::try_from(args.center.as_slice()) else {
+                bail!("Expected two coordinates of the centre");
+            };
+            check_width(args.width)?;
+            if let Some(width) = args.axial_width {
+                check_width(width)?;
+            }
+            Binning::Cylindrical {
+                axis: args.axis,
+                center,
+                radial_width: args.width,
+                axial_width: args.axial_width,
+            }
+        }
+        Commands::Spherical(args) => {
+            let Ok(center) = <[f64; 3]>::try_from(args.center.as_slice()) else {
+                bail!("Expected three coordinates of the centre");
+            };
+            check_width(args.width)?;
+            Binning::Spherical {
+                center,
+                width: args.width,
+            }
+        }
+    };
+    Ok(binning)
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    let binning = get_binning(&cli.command)?;
+    let keys = cli.keys.iter().map(String::as_str).collect::<Vec<_>>();
+    let dump = DumpFile::read(&cli.dump_file, &cli.timesteps)?;
+    let mut accumulator = BinAccumulator::new(binning, &keys);
+    for snapshot in dump.get_snapshots() {
+        accumulator.add_snapshot(snapshot);
+    }
+    info!("{} snapshots binned", accumulator.snapshots_count());
+    let table = accumulator
+        .bins()
+        .map(|(bin, stats)| {
+            let center = accumulator.binning().get_center(bin);
+            let values = (0..keys.len()).flat_map(|j| {
+                let (mean, std) = accumulator.get_mean(stats, j);
+                [mean, std, accumulator.get_sum(stats, j)]
+            });
+            center
+                .into_iter()
+                .chain([
+                    accumulator.get_count(stats),
+                    accumulator.get_density(bin, stats),
+                ])
+                .chain(values)
+                .map(|x| format!("{x:10.4}"))
+                .join("\t")
+        })
+        .join("\n");
+    let header = accumulator
+        .binning()
+        .get_coordinate_names()
+        .into_iter()
+        .map(ToString::to_string)
+        .chain(["count".to_string(), "density".to_string()])
+        .chain(
+            keys.iter()
+                .flat_map(|key| [key.to_string(), format!("σ({key})"), format!("sum({key})")]),
+        )
+        .join(" ");
+    println!("# {header}\n{table}");
+    Ok(())
+}
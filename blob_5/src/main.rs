@@ -1,7 +1,6 @@
-use anyhow::{Context, Error, Result, bail};
+use anyhow::{Context, Result, bail};
 use clap::Parser;
-use lammps_util_rust::{DumpFile, RunDir, get_avg_with_std, get_runs_dirs};
-use rayon::{ThreadPoolBuilder, prelude::*};
+use lammps_util_rust::{DumpFile, IteratorAvg, RunDir, Trajectory, process_results_dir};
 use std::{
     collections::HashMap,
     fs::File,
@@ -12,111 +11,68 @@ use std::{
 const N: usize = 5;
 const MAX_STEP: usize = 1000;
 
-#[derive(Clone)]
-struct Particle {
-    pos: [f64; 3],
-    _vel: [f64; 3],
-    ek: f64,
-    id: usize,
+struct Run {
+    trajectory: Trajectory,
+    times: Option<Vec<f64>>,
 }
 
-impl Particle {
-    fn new(pos: [f64; 3], vel: [f64; 3], ek: f64, id: usize) -> Self {
-        Self {
-            pos,
-            _vel: vel,
-            ek,
-            id,
-        }
+impl Run {
+    fn new(trajectory: Trajectory, times: Option<Vec<f64>>) -> Self {
+        Self { trajectory, times }
     }
-}
-
-struct Timestep {
-    particles: Vec<Particle>,
-    time: Option<f64>,
-    step: usize,
-}
 
-impl Timestep {
-    fn new(particles: Vec<Particle>, time: Option<f64>, step: usize) -> Self {
-        Self {
-            particles,
-            time,
-            step,
-        }
+    fn get_times(&self) -> Option<Vec<f64>> {
+        self.times.clone()
     }
 
     fn get_ids_sorted_by_z(&self) -> Vec<usize> {
-        let mut particles = self.particles.clone();
-        particles.sort_by(|a, b| a.pos[2].total_cmp(&b.pos[2]));
-        particles.into_iter().map(|p| p.id).collect()
-    }
-
-    fn get_energies_by_ids(&self, ids: &[usize]) -> Vec<f64> {
-        self.particles
+        let mut ids = self
+            .trajectory
+            .ids()
             .iter()
-            .filter(|p| ids.contains(&p.id))
-            .map(|p| p.ek)
-            .collect()
+            .enumerate()
+            .map(|(i, &id)| (id, self.trajectory.get_series_by_index("z", i)[0]))
+            .filter(|(_, z)| !z.is_nan())
+            .collect::<Vec<_>>();
+        ids.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ids.into_iter().map(|(id, _)| id).collect()
     }
 
-    fn get_bottom_ids(&self) -> [usize; N] {
-        let ids_sorted = self.get_ids_sorted_by_z();
-        let mut ids = [0; N];
-        ids.clone_from_slice(&ids_sorted[..N]);
-        ids
+    fn get_bottom_ids(&self) -> Vec<usize> {
+        self.get_ids_sorted_by_z().into_iter().take(N).collect()
     }
 
-    fn get_top_ids(&self) -> [usize; N] {
-        let ids_sorted = self.get_ids_sorted_by_z();
-        let mut ids = [0; N];
-        ids.clone_from_slice(&ids_sorted[ids_sorted.len() - N..]);
-        ids
-    }
-}
-
-struct Run {
-    timesteps: Vec<Timestep>,
-    _num: usize,
-}
-
-impl Run {
-    fn new(timesteps: Vec<Timestep>, num: usize) -> Self {
-        Self {
-            timesteps,
-            _num: num,
-        }
-    }
-
-    fn get_times(&self) -> Option<Vec<f64>> {
-        self.timesteps.iter().map(|r| r.time).collect()
+    fn get_top_ids(&self) -> Vec<usize> {
+        self.get_ids_sorted_by_z()
+            .into_iter()
+            .rev()
+            .take(N)
+            .collect()
     }
 
     fn get_avg_energies_by_ids(&self, ids: &[usize]) -> Vec<f64> {
-        self.timesteps
+        let series = ids
             .iter()
-            .map(|r| r.get_energies_by_ids(ids).iter().sum::<f64>() / N as f64)
+            .filter_map(|&id| self.trajectory.get_series("c_atom_ke", id))
+            .collect::<Vec<_>>();
+        (0..self.trajectory.steps_count())
+            .map(|i| {
+                series
+                    .iter()
+                    .map(|ek| ek[i])
+                    .filter(|ek| !ek.is_nan())
+                    .sum::<f64>()
+                    / N as f64
+            })
             .collect()
     }
 
     fn get_avg_bottom_energies(&self) -> Vec<f64> {
-        self.timesteps
-            .first()
-            .map(|timestep| {
-                let ids = timestep.get_bottom_ids();
-                self.get_avg_energies_by_ids(&ids)
-            })
-            .unwrap_or_default()
+        self.get_avg_energies_by_ids(&self.get_bottom_ids())
     }
 
     fn get_avg_top_energies(&self) -> Vec<f64> {
-        self.timesteps
-            .first()
-            .map(|timestep| {
-                let ids = timestep.get_top_ids();
-                self.get_avg_energies_by_ids(&ids)
-            })
-            .unwrap_or_default()
+        self.get_avg_energies_by_ids(&self.get_top_ids())
     }
 }
 
@@ -153,48 +109,34 @@ fn parse_time_from_log(path: &Path) -> Result<HashMap<usize, f64>> {
     Ok(times)
 }
 
-fn process_run_dir(run_dir: RunDir, is_read_time: bool) -> Result<Run> {
+fn process_run_dir(run_dir: &RunDir, is_read_time: bool) -> Result<Run> {
     let dump = DumpFile::read(&run_dir.path.join("dump.during"), &[])?;
+    let trajectory = Trajectory::new(
+        dump.get_snapshots()
+            .into_iter()
+            .filter(|s| s.step <= MAX_STEP as u64),
+        &["z", "c_atom_ke"],
+    );
     let times = if is_read_time {
-        Some(parse_time_from_log(&run_dir.path.join("log.lammps"))?)
-    } else {
-        None
-    };
-    let timesteps = dump
-        .get_snapshots()
-        .iter()
-        .filter(|s| s.step <= MAX_STEP as u64)
-        .map(|s| {
-            let x = s.get_property("x");
-            let y = s.get_property("y");
-            let z = s.get_property("z");
-            let vx = s.get_property("vx");
-            let vy = s.get_property("vy");
-            let vz = s.get_property("vz");
-            let ek = s.get_property("c_atom_ke");
-            let id = s.get_property("id");
-            let particles = (0..s.atoms_count)
-                .map(|i| {
-                    let pos = [x[i], y[i], z[i]];
-                    let vel = [vx[i], vy[i], vz[i]];
-                    Particle::new(pos, vel, ek[i], id[i] as usize)
-                })
-                .collect();
-            let step = s.step as usize;
-            let time = match &times {
-                Some(times_map) => Some(times_map.get(&step).copied().with_context(|| {
+        let times_map = parse_time_from_log(&run_dir.path.join("log.lammps"))?;
+        let times = trajectory
+            .steps()
+            .iter()
+            .map(|&step| {
+                times_map.get(&(step as usize)).copied().with_context(|| {
                     format!(
                         "No time data for timestep {}: {}",
                         step,
                         run_dir.path.to_string_lossy()
                     )
-                })?),
-                None => None,
-            };
-            Ok(Timestep::new(particles, time, step))
-        })
-        .collect::<Result<Vec<_>, Error>>()?;
-    Ok(Run::new(timesteps, run_dir.num))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Some(times)
+    } else {
+        None
+    };
+    Ok(Run::new(trajectory, times))
 }
 
 fn data_transpose(data: &[Vec<f64>]) -> Result<Vec<Vec<f64>>> {
@@ -213,7 +155,7 @@ fn data_transpose(data: &[Vec<f64>]) -> Result<Vec<Vec<f64>>> {
 fn parse_data(data: &[Vec<f64>]) -> Result<Vec<(f64, f64)>> {
     Ok(data_transpose(data)?
         .into_iter()
-        .filter_map(|inner| get_avg_with_std(&inner))
+        .filter_map(|inner| inner.into_iter().avg_with_std())
         .collect())
 }
 
@@ -242,13 +184,12 @@ fn get_times_data(runs: &[Run]) -> Option<Result<Vec<(f64, f64)>>> {
 }
 
 fn get_data(results_dir: &Path, threads: usize, is_read_time: bool) -> Result<Vec<Run>> {
-    let tp = ThreadPoolBuilder::new().num_threads(threads).build()?;
-    let dirs = get_runs_dirs(results_dir)?;
-    tp.install(|| {
-        dirs.into_par_iter()
-            .map(|d| process_run_dir(d, is_read_time))
-            .collect::<Result<Vec<_>>>()
-    })
+    Ok(
+        process_results_dir(results_dir, threads, |d| process_run_dir(d, is_read_time))?
+            .into_iter()
+            .map(|(_, run)| run)
+            .collect(),
+    )
 }
 
 #[derive(Parser)]
@@ -1,20 +1,168 @@
-use anyhow::{Context, Result};
-use clap::Parser;
-use lammps_util_rust::{DumpFile, DumpSnapshot, XYZ};
-use log::info;
-use std::collections::{HashMap, HashSet, VecDeque};
+use anyhow::{Context, Result, bail};
+use clap::{Args, Parser, Subcommand};
+use itertools::Itertools;
+use lammps_util_rust::{
+    DumpFile, DumpSnapshot, Hybridization, NeighborBackend, NeighborList, PairCutoffs, RingKind,
+    Rings, copy_snapshot_with_indices, get_hybridizations, hybridization_snapshot,
+    process_results_dir, rings_snapshot,
+};
+use log::{debug, info};
+use std::collections::{BTreeMap, VecDeque};
+use std::ops::AddAssign;
 use std::path::{Path, PathBuf};
 
 /// Analyze properties of carbon structres in a .dump file
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    /// Dump file
-    dump_file: PathBuf,
+    #[command(subcommand)]
+    command: Commands,
 
     /// Carbon atom type Id
     #[arg(short, long)]
     carbon_id: usize,
+
+    /// Bond cutoffs (A) like `1-1:2.7,1-2:2.2,2-2:1.85`, a bare number applies
+    /// to the pairs not listed. Required for single and multi, as C-C, Si-C and
+    /// Si-Si bonds differ in length, rings default to the C-C cutoff
+    #[arg(short = 'C', long)]
+    cutoffs: Option<PairCutoffs>,
+
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
+
+    /// Print the sp2 cluster size distribution, summed over the runs, instead
+    /// of the statistics
+    #[arg(short, long)]
+    sp2_sizes: bool,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Ring statistics of the carbon atoms in a dump file
+    Rings(RingsCMD),
+
+    /// Carbon hybridisation for a single run dir
+    Single(SingleCMD),
+
+    /// Carbon hybridisation for the whole results folder
+    Multi(MultiCMD),
+}
+
+#[derive(Args)]
+struct RingsCMD {
+    /// Dump file
+    dump_file: PathBuf,
+
+    /// Largest ring size to look for
+    #[arg(short, long, default_value_t = 10)]
+    max_size: usize,
+
+    /// Ring definition: primitive or king
+    #[arg(short, long, default_value = "primitive")]
+    kind: RingKind,
+
+    /// Dump file to write the carbon atoms with the ring columns to
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct SingleCMD {
+    run_dir: PathBuf,
+
+    /// Final dump file name inside the run dir
+    #[arg(short, long, default_value = "dump.final_no_cluster")]
+    dump_final: String,
+
+    /// Dump file name inside the run dir to write the carbon atoms with the
+    /// hybridization column to
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
+#[derive(Args)]
+struct MultiCMD {
+    results_dir: PathBuf,
+
+    /// Final dump file name inside a run dir
+    #[arg(short, long, default_value = "dump.final_no_cluster")]
+    dump_final: String,
+
+    /// Dump file name inside a run dir to write the carbon atoms with the
+    /// hybridization column to
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Number of threads to run in parallel
+    #[arg(short, long, default_value_t = 2)]
+    threads: usize,
+}
+
+/// C-C bond cutoff (A) of the ring search, between the sp2 and sp3 bond
+/// lengths and the second neighbours
+const CC_CUTOFF: f64 = 1.85;
+
+/// Carbon hybridisation and bonding of a run
+#[derive(Default)]
+struct CarbonStats {
+    carbon: usize,
+    /// Carbon atoms per [`Hybridization`]
+    hybridizations: [usize; 4],
+    cc_bonds: usize,
+    /// Bonds of carbon to the other types, Si in our runs
+    sic_bonds: usize,
+    sp2_clusters: usize,
+    sp2_max: usize,
+    /// Number of sp2 clusters per size
+    sp2_sizes: BTreeMap<usize, usize>,
+}
+
+impl AddAssign<&Self> for CarbonStats {
+    fn add_assign(&mut self, other: &Self) {
+        self.carbon += other.carbon;
+        for (sum, n) in self.hybridizations.iter_mut().zip(other.hybridizations) {
+            *sum += n;
+        }
+        self.cc_bonds += other.cc_bonds;
+        self.sic_bonds += other.sic_bonds;
+        self.sp2_clusters += other.sp2_clusters;
+        self.sp2_max = self.sp2_max.max(other.sp2_max);
+        for (&size, &count) in &other.sp2_sizes {
+            *self.sp2_sizes.entry(size).or_default() += count;
+        }
+    }
+}
+
+impl CarbonStats {
+    fn header() -> String {
+        std::iter::once("carbon")
+            .chain(Hybridization::ALL.map(Hybridization::name))
+            .chain(["cc_bonds", "sic_bonds", "sp2_clusters", "sp2_max"])
+            .join(" ")
+    }
+
+    fn row(&self) -> String {
+        std::iter::once(self.carbon)
+            .chain(Hybridization::ALL.map(|h| self.hybridizations[h as usize]))
+            .chain([
+                self.cc_bonds,
+                self.sic_bonds,
+                self.sp2_clusters,
+                self.sp2_max,
+            ])
+            .join("\t")
+    }
+
+    fn sp2_sizes_table(&self) -> String {
+        let table = self
+            .sp2_sizes
+            .iter()
+            .map(|(size, count)| format!("{size}\t{count}"))
+            .join("\n");
+        format!("# size count\n{table}")
+    }
 }
 
 fn load_snapshot(path: &Path) -> Result<DumpSnapshot> {
@@ -26,134 +174,213 @@ fn load_snapshot(path: &Path) -> Result<DumpSnapshot> {
     Ok(snapshot.to_owned())
 }
 
-fn get_carbon_atoms(snapshot: &DumpSnapshot, type_id: usize) -> Vec<XYZ> {
-    let coords = snapshot.get_coordinates();
+fn get_carbon_atoms(snapshot: &DumpSnapshot, type_id: usize) -> DumpSnapshot {
     let types = snapshot.get_property("type");
-    coords
-        .iter()
-        .filter(|xyz| (types[xyz.index()] as usize).eq(&type_id))
-        .copied()
-        .collect()
-}
-
-struct RingsFinder {
-    adjecency_list: HashMap<XYZ, Vec<XYZ>>,
+    let indices = (0..snapshot.atoms_count).filter(|&i| types[i] as usize == type_id);
+    copy_snapshot_with_indices(snapshot, indices)
 }
 
-impl RingsFinder {
-    pub fn new(atoms: Vec<XYZ>) -> Self {
-        let mut adjecency_list = HashMap::new();
-        let tree = kd_tree::KdTree::build_by_ordered_float(atoms.clone());
-        for atom in atoms {
-            let inner = tree.within_radius(&atom, 1.5);
-            let outer = tree.within_radius(&atom, 1.7);
-            let neighbours = outer
-                .iter()
-                .filter(|atom| !inner.contains(atom))
-                .map(|atom| **atom)
-                .collect::<Vec<_>>();
-            adjecency_list.entry(atom).insert_entry(neighbours);
+/// Connected structure of every selected atom, numbered from 0 by
+/// decreasing size, and the structure sizes
+fn get_structures(neighbors: &NeighborList, selected: &[bool]) -> (Vec<usize>, Vec<usize>) {
+    let mut structures = vec![usize::MAX; neighbors.len()];
+    let mut sizes = Vec::new();
+    for start in 0..neighbors.len() {
+        if structures[start] != usize::MAX || !selected[start] {
+            continue;
         }
-        Self { adjecency_list }
-    }
-
-    pub fn find(&self) -> HashMap<usize, usize> {
-        let mut rings = Vec::new();
-        for (&atom_begin, neighbours) in self.adjecency_list.iter() {
-            for &atom_end in neighbours {
-                if let Some(ring) = self.bfs(atom_begin, atom_end) {
-                    if !rings.contains(&ring) {
-                        rings.push(ring);
-                    }
+        let id = sizes.len();
+        structures[start] = id;
+        let mut size = 0;
+        let mut queue = VecDeque::from([start]);
+        while let Some(atom) = queue.pop_front() {
+            size += 1;
+            for neigh in neighbors.neighbors(atom) {
+                if structures[neigh.index] == usize::MAX && selected[neigh.index] {
+                    structures[neigh.index] = id;
+                    queue.push_back(neigh.index);
                 }
             }
         }
-        let mut rings_cnt = HashMap::new();
-        for ring in rings {
-            rings_cnt
-                .entry(ring.len())
-                .and_modify(|c| *c += 1)
-                .or_insert(1);
-        }
-        rings_cnt
+        sizes.push(size);
     }
-
-    fn bfs(&self, atom_begin: XYZ, atom_end: XYZ) -> Option<HashSet<XYZ>> {
-        let mut queue = VecDeque::from([(atom_begin, HashSet::from([atom_begin]))]);
-        let mut visited = HashSet::new();
-        while let Some((atom, ring)) = queue.pop_front() {
-            if visited.contains(&atom) {
-                continue;
-            }
-            visited.insert(atom);
-            for &atom_next in self.adjecency_list[&atom].iter() {
-                let mut ring = ring.clone();
-                if atom == atom_begin && atom_next == atom_end {
-                    continue;
-                }
-                ring.insert(atom_next);
-                if atom_next == atom_end {
-                    return Some(ring);
-                }
-                queue.push_back((atom_next, ring));
-            }
-        }
-        None
+    let order = (0..sizes.len())
+        .sorted_by_key(|&id| std::cmp::Reverse(sizes[id]))
+        .collect::<Vec<_>>();
+    let mut ranks = vec![0; sizes.len()];
+    for (rank, &id) in order.iter().enumerate() {
+        ranks[id] = rank;
     }
+    let structures = structures
+        .into_iter()
+        .map(|id| ranks.get(id).copied().unwrap_or(usize::MAX))
+        .collect();
+    (structures, order.into_iter().map(|id| sizes[id]).collect())
 }
 
-fn main() -> Result<()> {
-    env_logger::init();
-    let cli = Cli::parse();
-    let snapshot = load_snapshot(&cli.dump_file)?;
-    let atoms = get_carbon_atoms(&snapshot, cli.carbon_id);
-    info!("Loaded {} carbon atoms", atoms.len());
-    let rings = RingsFinder::new(atoms).find();
-    println!("Rings: {rings:?}");
+fn print_rings(
+    args: &RingsCMD,
+    carbon_id: usize,
+    cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
+) -> Result<()> {
+    let snapshot = load_snapshot(&args.dump_file)?;
+    let carbon = get_carbon_atoms(&snapshot, carbon_id);
+    info!("Loaded {} carbon atoms", carbon.atoms_count);
+    let neighbors = NeighborList::new_with_cutoffs(&carbon, cutoffs, backend);
+    let rings = Rings::new(&neighbors, args.max_size, args.kind);
+    info!("Found {} rings", rings.rings().len());
+    let (structures, _) = get_structures(&neighbors, &vec![true; neighbors.len()]);
+    let mut atoms = BTreeMap::<usize, usize>::new();
+    for &structure in &structures {
+        *atoms.entry(structure).or_default() += 1;
+    }
+    let mut sizes = BTreeMap::<usize, BTreeMap<usize, usize>>::new();
+    for ring in rings.rings() {
+        *sizes
+            .entry(structures[ring[0]])
+            .or_default()
+            .entry(ring.len())
+            .or_default() += 1;
+    }
+    let total = rings.get_size_counts();
+    let row = |name: String, atoms: usize, counts: &BTreeMap<usize, usize>| {
+        [
+            name,
+            atoms.to_string(),
+            counts.values().sum::<usize>().to_string(),
+        ]
+        .into_iter()
+        .chain((3..=args.max_size).map(|n| counts.get(&n).copied().unwrap_or(0).to_string()))
+        .join("\t")
+    };
+    let table = sizes
+        .iter()
+        .map(|(structure, counts)| row(structure.to_string(), atoms[structure], counts))
+        .chain(std::iter::once(row(
+            "all".to_string(),
+            carbon.atoms_count,
+            &total,
+        )))
+        .join("\n");
+    let header = ["structure", "atoms", "rings"]
+        .into_iter()
+        .map(str::to_string)
+        .chain((3..=args.max_size).map(|n| format!("ring_{n}")))
+        .join(" ");
+    println!("# {header}\n{table}");
+    if let Some(output) = &args.output {
+        let carbon = rings_snapshot(&carbon, &rings, args.max_size);
+        DumpFile::new(vec![carbon]).save(output)?;
+    }
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_triangle_graph() {
-        let node0 = XYZ::from([0.0, 0.0, 0.0], 0);
-        let node1 = XYZ::from([1.6, 0.0, 0.0], 0);
-        let node2 = XYZ::from([0.8, 1.3856, 0.0], 0);
-
-        let nodes = vec![node0, node1, node2];
-        let finder = RingsFinder::new(nodes);
-
-        for (_, neighbours) in finder.adjecency_list.iter() {
-            assert_eq!(neighbours.len(), 2);
+fn analyze_single_run(
+    dir: &Path,
+    dump_final: &str,
+    output: Option<&str>,
+    carbon_id: usize,
+    cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
+) -> Result<CarbonStats> {
+    let snapshot = load_snapshot(&dir.join(dump_final))?;
+    let types = snapshot.get_property("type");
+    let is_carbon = types
+        .iter()
+        .map(|&t| t as usize == carbon_id)
+        .collect::<Vec<_>>();
+    let neighbors = NeighborList::new_with_cutoffs(&snapshot, cutoffs, backend);
+    let hybridizations = get_hybridizations(&neighbors);
+    let mut stats = CarbonStats::default();
+    for (_, hybridization) in is_carbon.iter().zip(&hybridizations).filter(|(c, _)| **c) {
+        stats.carbon += 1;
+        stats.hybridizations[*hybridization as usize] += 1;
+    }
+    for (i, neigh) in neighbors.pairs() {
+        match (is_carbon[i], is_carbon[neigh.index]) {
+            (true, true) => stats.cc_bonds += 1,
+            (true, false) | (false, true) => stats.sic_bonds += 1,
+            (false, false) => {}
         }
-
-        let rings = finder.find();
-        let expected_rings: HashMap<usize, usize> = HashMap::from([(3, 1)]);
-
-        assert_eq!(rings, expected_rings);
     }
+    let sp2 = is_carbon
+        .iter()
+        .zip(&hybridizations)
+        .map(|(c, h)| *c && *h == Hybridization::Sp2)
+        .collect::<Vec<_>>();
+    let (_, sizes) = get_structures(&neighbors, &sp2);
+    stats.sp2_clusters = sizes.len();
+    stats.sp2_max = sizes.first().copied().unwrap_or(0);
+    for size in sizes {
+        *stats.sp2_sizes.entry(size).or_default() += 1;
+    }
+    debug!("{}: {} carbon atoms", dir.display(), stats.carbon);
+    if let Some(output) = output {
+        let snapshot = hybridization_snapshot(&snapshot, &hybridizations);
+        let carbon = copy_snapshot_with_indices(
+            &snapshot,
+            (0..snapshot.atoms_count).filter(|&i| is_carbon[i]),
+        );
+        DumpFile::new(vec![carbon]).save(&dir.join(output))?;
+    }
+    Ok(stats)
+}
 
-    #[test]
-    fn test_two_triangles_graph() {
-        let node0 = XYZ::from([0.0, 0.0, 0.0], 0);
-        let node1 = XYZ::from([1.6, 0.0, 0.0], 0);
-        let node2 = XYZ::from([0.8, 1.3856, 0.0], 0);
-        let node3 = XYZ::from([0.8, -1.3856, 0.0], 0);
-
-        let nodes = vec![node0, node1, node2, node3];
-        let finder = RingsFinder::new(nodes);
-
-        assert_eq!(finder.adjecency_list[&node0].len(), 3);
-        assert_eq!(finder.adjecency_list[&node1].len(), 3);
-        assert_eq!(finder.adjecency_list[&node2].len(), 2);
-        assert_eq!(finder.adjecency_list[&node3].len(), 2);
-
-        let rings = finder.find();
-        let expected_rings: HashMap<usize, usize> = HashMap::from([(3, 2)]);
-
-        assert_eq!(rings, expected_rings);
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    let cutoffs = match (&cli.cutoffs, &cli.command) {
+        (Some(cutoffs), _) => cutoffs.clone(),
+        (None, Commands::Rings(_)) => PairCutoffs::new(CC_CUTOFF),
+        (None, _) => {
+            bail!("Bond cutoffs of every type pair are required, ex. -C 1-1:2.7,1-2:2.2,2-2:1.85")
+        }
+    };
+    let header = CarbonStats::header();
+    match &cli.command {
+        Commands::Rings(args) => print_rings(args, cli.carbon_id, &cutoffs, cli.backend)?,
+        Commands::Single(args) => {
+            let stats = analyze_single_run(
+                &args.run_dir,
+                &args.dump_final,
+                args.output.as_deref(),
+                cli.carbon_id,
+                &cutoffs,
+                cli.backend,
+            )?;
+            if cli.sp2_sizes {
+                println!("{}", stats.sp2_sizes_table());
+            } else {
+                println!("# {header}\n{}", stats.row());
+            }
+        }
+        Commands::Multi(args) => {
+            let results = process_results_dir(&args.results_dir, args.threads, |dir| {
+                analyze_single_run(
+                    &dir.path,
+                    &args.dump_final,
+                    args.output.as_deref(),
+                    cli.carbon_id,
+                    &cutoffs,
+                    cli.backend,
+                )
+            })?;
+            let mut total = CarbonStats::default();
+            for (_, stats) in &results {
+                total += stats;
+            }
+            if cli.sp2_sizes {
+                println!("{}", total.sp2_sizes_table());
+                return Ok(());
+            }
+            let table = results
+                .iter()
+                .map(|(dir, stats)| format!("{}\t{}", dir.num, stats.row()))
+                .chain(std::iter::once(format!("all\t{}", total.row())))
+                .join("\n");
+            println!("# run {header}\n{table}");
+        }
     }
+    Ok(())
 }
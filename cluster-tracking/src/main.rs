@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use itertools::Itertools;
+use lammps_util_rust::{
+    ClusterEvent, ClusterTracker, DumpFile, NeighborBackend, PairCutoffs, clusterize_snapshot,
+    copy_snapshot_with_indices, process_results_dir,
+};
+use log::info;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Follow the sputtered clusters through the trajectory and print their
+/// fragmentation and merging events
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    /// Clustering cutoffs (A), a bare number or pairs like `1-1:3.0,1-2:2.5`
+    #[arg(short, long, default_value = "3.0")]
+    cutoffs: PairCutoffs,
+
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
+
+    /// Trajectory dump file name inside a run dir
+    #[arg(short, long, default_value = "dump.during")]
+    during: String,
+
+    /// Dump file name with the sputtered atoms inside a run dir
+    #[arg(short, long, default_value = "dump.sputter")]
+    sputter: String,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Cluster lineage for a single run dir
+    Single(SingleCMD),
+
+    /// Cluster lineage for the whole results folder
+    Multi(MultiCMD),
+}
+
+#[derive(Args)]
+struct SingleCMD {
+    run_dir: PathBuf,
+}
+
+#[derive(Args)]
+struct MultiCMD {
+    results_dir: PathBuf,
+
+    /// Number of threads to run in parallel
+    #[arg(short, long, default_value_t = 2)]
+    threads: usize,
+}
+
+const HEADER: &str = "timestep event parents children parent_sizes children_sizes";
+
+fn track_run_dir(run_dir: &Path, cli: &Cli) -> Result<Vec<ClusterEvent>> {
+    let sputter_path = run_dir.join(&cli.sputter);
+    let dump_sputter = DumpFile::read(&sputter_path, &[])
+        .context(format!("Failed to read {}", sputter_path.display()))?;
+    let sputtered = dump_sputter.get_snapshots()[0]
+        .get_property("id")
+        .iter()
+        .map(|&id| id as usize)
+        .collect::<HashSet<_>>();
+    let during_path = run_dir.join(&cli.during);
+    let dump_during = DumpFile::read(&during_path, &[])
+        .context(format!("Failed to read {}", during_path.display()))?;
+    let mut tracker = ClusterTracker::new();
+    for snapshot in dump_during.get_snapshots() {
+        let ids = snapshot.get_property("id");
+        let indices = (0..snapshot.atoms_count).filter(|&i| sputtered.contains(&(ids[i] as usize)));
+        let snapshot = copy_snapshot_with_indices(snapshot, indices);
+        tracker.add_snapshot(&clusterize_snapshot(&snapshot, &cli.cutoffs, cli.backend));
+    }
+    info!(
+        "{}: {} events, {} clusters at the end",
+        run_dir.display(),
+        tracker.events().len(),
+        tracker.clusters().len()
+    );
+    Ok(tracker.events().to_vec())
+}
+
+fn format_event(event: &ClusterEvent) -> String {
+    let ids = |clusters: &[(usize, usize)]| clusters.iter().map(|(id, _)| id).join(",");
+    let sizes = |clusters: &[(usize, usize)]| clusters.iter().map(|(_, n)| n).join(",");
+    // an empty list is written as `-` to keep the columns whitespace separated
+    [
+        event.timestep.to_string(),
+        event.kind.name().to_string(),
+        ids(&event.parents),
+        ids(&event.children),
+        sizes(&event.parents),
+        sizes(&event.children),
+    ]
+    .map(|s| if s.is_empty() { "-".to_string() } else { s })
+    .join("\t")
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    match &cli.command {
+        Commands::Single(args) => {
+            let events = track_run_dir(&args.run_dir, &cli)?;
+            let table = events.iter().map(format_event).join("\n");
+            println!("# {HEADER}\n{table}");
+        }
+        Commands::Multi(args) => {
+            let results = process_results_dir(&args.results_dir, args.threads, |dir| {
+                track_run_dir(&dir.path, &cli)
+            })?;
+            let table = results
+                .iter()
+                .flat_map(|(dir, events)| {
+                    events
+                        .iter()
+                        .map(|event| format!("{}\t{}", dir.num, format_event(event)))
+                })
+                .join("\n");
+            println!("# run {HEADER}\n{table}");
+        }
+    }
+    Ok(())
+}
@@ -2,7 +2,9 @@ use std::{iter, path::PathBuf};
 
 use anyhow::Result;
 use clap::Parser;
-use lammps_util_rust::{crater_snapshot, geomutil_util::Point3, DumpFile, DumpSnapshot, XYZ};
+use lammps_util_rust::{
+    crater_snapshot, DumpFile, DumpSnapshot, NeighborBackend, PairCutoffs, Vector3,
+};
 use log::debug;
 
 #[derive(Parser)]
@@ -13,24 +15,44 @@ struct Cli {
 
     /// Dump initial
     dump_final: PathBuf,
+
+    /// Cutoffs (A) clustering the crater atoms, a bare number or pairs like
+    /// `1-1:3.0,1-2:2.5`
+    #[arg(short = 'C', long, default_value = "3.0")]
+    cluster_cutoffs: PairCutoffs,
+
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
 }
 
-fn get_coords_shift(a: &[XYZ], b: &[XYZ]) -> (usize, Point3, Point3) {
+fn get_coords_shift(a: &[Vector3<f64>], b: &[Vector3<f64>]) -> (usize, Vector3<f64>, Vector3<f64>) {
     let deltas = iter::zip(a, b)
-        .map(|(a, b)| **b - **a)
-        .collect::<Vec<Point3>>();
+        .map(|(a, b)| *b - *a)
+        .collect::<Vec<Vector3<f64>>>();
     let count = deltas.len();
     let sum = deltas.iter().copied().reduce(|a, b| a + b).unwrap();
     let sum2 = deltas
         .into_iter()
-        .map(|a| a * a)
+        .map(|a| a.map(|c| c * c))
         .reduce(|a, b| a + b)
         .unwrap();
     (count, sum, sum2)
 }
 
-fn get_ids(input_snapshot: &DumpSnapshot, final_snapshot: &DumpSnapshot) -> Vec<f64> {
-    let crater_snapshot = crater_snapshot(input_snapshot, final_snapshot, 1.75, 3.0);
+fn get_ids(
+    input_snapshot: &DumpSnapshot,
+    final_snapshot: &DumpSnapshot,
+    cluster_cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
+) -> Vec<f64> {
+    let crater_snapshot = crater_snapshot(
+        input_snapshot,
+        final_snapshot,
+        1.75,
+        cluster_cutoffs,
+        backend,
+    );
     let final_ids = final_snapshot.get_property("id");
     let crater_ids = crater_snapshot.get_property("id");
     let ids = crater_ids
@@ -42,18 +64,20 @@ fn get_ids(input_snapshot: &DumpSnapshot, final_snapshot: &DumpSnapshot) -> Vec<
     ids
 }
 
-fn get_coords_filtered(snapshot: &DumpSnapshot, ids: &[f64]) -> Vec<XYZ> {
-    iter::zip(snapshot.get_coordinates(), snapshot.get_property("id"))
+fn get_coords_filtered(snapshot: &DumpSnapshot, ids: &[f64]) -> Vec<Vector3<f64>> {
+    iter::zip(snapshot.get_positions(), snapshot.get_property("id"))
         .filter(|(_, id)| ids.contains(id))
-        .map(|(xyz, _)| xyz)
+        .map(|(position, _)| position)
         .collect()
 }
 
 fn get_coords(
     input_snapshot: &DumpSnapshot,
     final_snapshot: &DumpSnapshot,
-) -> (Vec<XYZ>, Vec<XYZ>) {
-    let ids = get_ids(input_snapshot, final_snapshot);
+    cluster_cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
+) -> (Vec<Vector3<f64>>, Vec<Vector3<f64>>) {
+    let ids = get_ids(input_snapshot, final_snapshot, cluster_cutoffs, backend);
     (
         get_coords_filtered(input_snapshot, &ids),
         get_coords_filtered(final_snapshot, &ids),
@@ -70,7 +94,12 @@ fn main() -> Result<()> {
     let dump_final = DumpFile::read(&cli.dump_final, &[])?;
     let final_snapshot = dump_final.get_snapshots()[0];
 
-    let (input_coords, final_coords) = get_coords(initial_snapshot, final_snapshot);
+    let (input_coords, final_coords) = get_coords(
+        initial_snapshot,
+        final_snapshot,
+        &cli.cluster_cutoffs,
+        cli.backend,
+    );
     let (cnt, sum, sum2) = get_coords_shift(&input_coords, &final_coords);
     println!("{cnt}");
     println!("{} {} {}", sum.x, sum.y, sum.z);
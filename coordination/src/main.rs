@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use itertools::Itertools;
+use lammps_util_rust::{
+    Coordination, DumpFile, NeighborBackend, PairCutoffs, coordination_snapshot,
+};
+use log::info;
+use std::{collections::BTreeMap, iter, path::PathBuf};
+
+/// Coordination numbers with per type pair cutoffs
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    dump_file: PathBuf,
+
+    /// Cutoffs (A) like `1-1:2.6,1-2:2.1,2-2:1.9`, a bare number applies to
+    /// the pairs not listed
+    #[arg(short, long)]
+    cutoffs: PairCutoffs,
+
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
+
+    #[arg(short, long)]
+    timestep: Option<u64>,
+
+    /// Dump file to write with the coordination columns added
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct Bin {
+    count: usize,
+    partials: Vec<usize>,
+}
+
+/// Histogram of the coordination numbers keyed by atom type and coordination
+fn get_histogram(coordination: &Coordination) -> BTreeMap<(usize, usize), Bin> {
+    let mut histogram = BTreeMap::<_, Bin>::new();
+    for atom_i in 0..coordination.atoms_count() {
+        let key = (
+            coordination.get_atom_type(atom_i),
+            coordination.get_total(atom_i),
+        );
+        let bin = histogram.entry(key).or_default();
+        bin.count += 1;
+        bin.partials.resize(coordination.types().len(), 0);
+        iter::zip(&mut bin.partials, coordination.get_partials(atom_i))
+            .for_each(|(sum, n)| *sum += n);
+    }
+    histogram
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    let timesteps = cli.timestep.map(|t| vec![t]).unwrap_or_default();
+    let dump = DumpFile::read(&cli.dump_file, &timesteps)?;
+    let snapshot = *dump.get_snapshots().first().context("No snapshots")?;
+    info!(
+        "step {}: {} atoms, max cutoff {}",
+        snapshot.step,
+        snapshot.atoms_count,
+        cli.cutoffs.max_cutoff()
+    );
+    let coordination = Coordination::new(snapshot, &cli.cutoffs, cli.backend);
+    let histogram = get_histogram(&coordination);
+    let type_counts = histogram.iter().fold(
+        BTreeMap::<usize, usize>::new(),
+        |mut counts, ((t, _), bin)| {
+            *counts.entry(*t).or_default() += bin.count;
+            counts
+        },
+    );
+    let table = histogram
+        .iter()
+        .map(|(&(atype, cn), bin)| {
+            let fraction = bin.count as f64 / type_counts[&atype] as f64;
+            let partials = bin
+                .partials
+                .iter()
+                .map(|sum| format!("{:10.4}", *sum as f64 / bin.count as f64));
+            [atype.to_string(), cn.to_string(), bin.count.to_string()]
+                .into_iter()
+                .chain(iter::once(format!("{fraction:10.4}")))
+                .chain(partials)
+                .join("\t")
+        })
+        .join("\n");
+    let partials_header = coordination
+        .types()
+        .iter()
+        .map(|t| format!("cn_{t}"))
+        .join(" ");
+    println!("# type cn count fraction {partials_header}\n{table}");
+    if let Some(output) = &cli.output {
+        let snapshot = coordination_snapshot(snapshot, &coordination);
+        DumpFile::new(vec![snapshot]).save(output)?;
+    }
+    Ok(())
+}
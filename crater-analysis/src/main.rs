@@ -1,6 +1,9 @@
 use anyhow::Result;
 use clap::{Args, Parser, Subcommand};
-use lammps_util_rust::{crater_snapshot, process_results_dir, DumpFile, DumpSnapshot, IteratorAvg};
+use lammps_util_rust::{
+    crater_snapshot, process_results_dir, DumpFile, DumpSnapshot, IteratorAvg, NeighborBackend,
+    PairCutoffs,
+};
 use log::debug;
 use std::path::{Path, PathBuf};
 
@@ -17,6 +20,15 @@ struct Cli {
     /// Cutoff (A)
     #[arg(short, long, default_value_t = 1.75)]
     cutoff: f64,
+
+    /// Cutoffs (A) clustering the crater atoms, a bare number or pairs like
+    /// `1-1:3.0,1-2:2.5`
+    #[arg(short = 'C', long, default_value = "3.0")]
+    cluster_cutoffs: PairCutoffs,
+
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
 }
 
 #[derive(Subcommand)]
@@ -58,13 +70,19 @@ fn get_crater_info(snapshot: &DumpSnapshot, zero_lvl: f64) -> String {
     format!("{crater_count} {volume} {surface} {z_avg} {z_min}")
 }
 
-fn analyze_single_run(dir: &Path, cutoff: f64, _depth: f64) -> Result<String> {
+fn analyze_single_run(dir: &Path, cli: &Cli) -> Result<String> {
     let dump_input = DumpFile::read(&dir.join("dump.initial"), &[])?;
     let snapshot_input = dump_input.get_snapshots()[0];
     let zero_lvl = snapshot_input.get_zero_lvl();
     let dump_final = DumpFile::read(&dir.join("dump.final_no_cluster"), &[])?;
     let snapshot_final = dump_final.get_snapshots()[0];
-    let snapshot_crater = crater_snapshot(snapshot_input, snapshot_final, cutoff, 3.0);
+    let snapshot_crater = crater_snapshot(
+        snapshot_input,
+        snapshot_final,
+        cli.cutoff,
+        &cli.cluster_cutoffs,
+        cli.backend,
+    );
     debug!("crater atoms: {}", snapshot_crater.atoms_count);
     let info = get_crater_info(&snapshot_crater, zero_lvl);
     let dump_crater = DumpFile::new(vec![snapshot_crater]);
@@ -72,10 +90,8 @@ fn analyze_single_run(dir: &Path, cutoff: f64, _depth: f64) -> Result<String> {
     Ok(info)
 }
 
-fn analyze_results_dir(dir: &Path, threads: usize, cutoff: f64, depth: f64) -> Result<String> {
-    let results = process_results_dir(dir, threads, |dir| {
-        analyze_single_run(&dir.path, cutoff, depth)
-    })?;
+fn analyze_results_dir(dir: &Path, threads: usize, cli: &Cli) -> Result<String> {
+    let results = process_results_dir(dir, threads, |dir| analyze_single_run(&dir.path, cli))?;
     let info = results
         .iter()
         .map(|(dir, info)| format!("{} {info}", dir.num))
@@ -88,10 +104,8 @@ fn main() -> Result<()> {
     env_logger::init();
     let cli = Cli::parse();
     let info = match &cli.command {
-        Commands::Single(args) => analyze_single_run(&args.run_dir, cli.cutoff, cli.max_depth)?,
-        Commands::Multi(args) => {
-            analyze_results_dir(&args.results_dir, args.threads, cli.cutoff, cli.max_depth)?
-        }
+        Commands::Single(args) => analyze_single_run(&args.run_dir, &cli)?,
+        Commands::Multi(args) => analyze_results_dir(&args.results_dir, args.threads, &cli)?,
     };
     println!("{info}");
     Ok(())
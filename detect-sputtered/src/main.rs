@@ -1,9 +1,9 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::{Args, Parser, Subcommand};
 use lammps_util_rust::{
-    DumpFile, clusterize_snapshot, copy_snapshot_with_indices, get_cluster_counts, get_runs_dirs,
+    DumpFile, NeighborBackend, PairCutoffs, SputterCriteria, SputterDetector,
+    copy_snapshot_with_indices, process_results_dir,
 };
-use rayon::{ThreadPoolBuilder, prelude::*};
 use std::path::{Path, PathBuf};
 
 #[derive(Parser)]
@@ -11,6 +11,28 @@ use std::path::{Path, PathBuf};
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Clustering cutoffs (A), a bare number or pairs like `1-1:3.0,1-2:2.5`
+    #[arg(short, long, default_value = "3.0")]
+    cutoffs: PairCutoffs,
+
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
+
+    /// Sputter criteria joined with `&` (all) or `|` (any) from `height>A`,
+    /// `vz>A/ps`, `energy>eV` and `size<N`, ex. "size<1000&height>2&vz>0"
+    #[arg(short, long, default_value = "size<1000")]
+    sputter: SputterCriteria,
+
+    /// Zero level for the height criterion, the top of dump.initial by default
+    #[arg(short, long)]
+    zero_lvl: Option<f64>,
+
+    /// Masses of the types (amu) for the energy criterion when the dumps have
+    /// no `mass` column
+    #[arg(short, long, value_delimiter = ',')]
+    masses: Vec<f64>,
 }
 
 #[derive(Subcommand)]
@@ -36,30 +58,40 @@ struct MultiCMD {
     threads: usize,
 }
 
-fn do_run_dir(run_dir: &Path) -> Result<()> {
-    let dump_final = DumpFile::read(&run_dir.join("dump.final"), &[])?;
-    let snapshot_final = clusterize_snapshot(dump_final.get_snapshots()[0], 3.0);
-    let counts = get_cluster_counts(&snapshot_final);
-    let cluster_ids = counts
+fn get_detector(run_dir: &Path, cli: &Cli) -> Result<SputterDetector> {
+    let type_masses = [0.0]
         .into_iter()
-        .filter(|(_, count)| *count < 1000)
-        .map(|(id, _)| id)
-        .collect::<Vec<_>>();
-    let clusters = snapshot_final.get_property("cluster");
-    let mut sputter_indices = Vec::new();
-    let mut no_sputter_indices = Vec::new();
-    for (i, cluster) in clusters
-        .iter()
-        .map(|id| *id as usize)
-        .enumerate()
-        .take(snapshot_final.atoms_count)
-    {
-        if cluster_ids.contains(&cluster) {
-            sputter_indices.push(i);
-        } else {
-            no_sputter_indices.push(i);
+        .chain(cli.masses.iter().copied())
+        .collect();
+    let detector = SputterDetector::new(cli.cutoffs.clone(), cli.sputter.clone())
+        .with_backend(cli.backend)
+        .with_type_masses(type_masses);
+    let zero_lvl = match cli.zero_lvl {
+        Some(zero_lvl) => zero_lvl,
+        None if cli.sputter.needs_zero_lvl() => {
+            let dump_initial = DumpFile::read(&run_dir.join("dump.initial"), &[])?;
+            dump_initial.get_snapshots()[0].get_zero_lvl()
         }
+        None => return Ok(detector),
+    };
+    Ok(detector.with_zero_lvl(zero_lvl))
+}
+
+fn do_run_dir(run_dir: &Path, cli: &Cli) -> Result<()> {
+    let dump_final = DumpFile::read(&run_dir.join("dump.final"), &[])?;
+    let snapshot_final = dump_final.get_snapshots()[0];
+    let detector = get_detector(run_dir, cli)?;
+    let missing = detector.get_missing_mass_types(snapshot_final);
+    if !missing.is_empty() {
+        bail!(
+            "{}: no mass column and no --masses for the types {missing:?}",
+            run_dir.display()
+        );
     }
+    let snapshot_final = detector.detect(snapshot_final);
+    let sputtered = snapshot_final.get_property("sputtered");
+    let (sputter_indices, no_sputter_indices): (Vec<_>, Vec<_>) =
+        (0..snapshot_final.atoms_count).partition(|&i| sputtered[i] > 0.0);
     let snapshot_sputter = copy_snapshot_with_indices(&snapshot_final, sputter_indices.into_iter());
     println!("sputtered: {}", snapshot_sputter.atoms_count);
     let dump_sputter = DumpFile::new(vec![snapshot_sputter]);
@@ -71,15 +103,8 @@ fn do_run_dir(run_dir: &Path) -> Result<()> {
     Ok(())
 }
 
-fn do_results_dir(results_dir: &Path, threads: usize) -> Result<()> {
-    let tp = ThreadPoolBuilder::new().num_threads(threads).build()?;
-    let run_dirs = get_runs_dirs(results_dir)?;
-    tp.install(|| {
-        run_dirs
-            .into_par_iter()
-            .map(|dir| do_run_dir(&dir.path))
-            .collect::<Result<Vec<_>>>()
-    })?;
+fn do_results_dir(results_dir: &Path, threads: usize, cli: &Cli) -> Result<()> {
+    process_results_dir(results_dir, threads, |dir| do_run_dir(&dir.path, cli))?;
     Ok(())
 }
 
@@ -88,8 +113,8 @@ fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match &cli.command {
-        Commands::Single(args) => do_run_dir(&args.run_dir)?,
-        Commands::Multi(args) => do_results_dir(&args.results_dir, args.threads)?,
+        Commands::Single(args) => do_run_dir(&args.run_dir, &cli)?,
+        Commands::Multi(args) => do_results_dir(&args.results_dir, args.threads, &cli)?,
     };
     Ok(())
 }
@@ -0,0 +1,139 @@
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use itertools::Itertools;
+use lammps_util_rust::{
+    DiamondStructure, DumpFile, DumpSnapshot, IteratorAvg, diamond_structure_snapshot,
+    process_results_dir,
+};
+use log::debug;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// Amorphisation below the crater from the identify diamond structure analysis
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    /// Radius to search the four nearest neighbours in (A)
+    #[arg(short, long, default_value_t = 3.0)]
+    search_radius: f64,
+
+    /// Final dump file name inside a run dir
+    #[arg(short, long, default_value = "dump.final_no_cluster")]
+    dump_final: String,
+
+    /// Volume per atom of the crystal (A^3), 20.1 for Si
+    #[arg(short, long, default_value_t = 20.1)]
+    atom_volume: f64,
+
+    /// Dump file name inside a run dir to write the final atoms with the
+    /// structure column to
+    #[arg(short, long)]
+    output: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Amorphisation for a single run dir
+    Single(SingleCMD),
+
+    /// Amorphisation for the whole results folder
+    Multi(MultiCMD),
+}
+
+#[derive(Args)]
+struct SingleCMD {
+    run_dir: PathBuf,
+}
+
+#[derive(Args)]
+struct MultiCMD {
+    results_dir: PathBuf,
+
+    /// Number of threads to run in parallel
+    #[arg(short, long, default_value_t = 2)]
+    threads: usize,
+}
+
+/// Ids of the atoms labelled with any of the diamond types, or of the
+/// remaining ones when `diamond` is false
+fn get_ids(snapshot: &DumpSnapshot, diamond: bool) -> HashSet<u64> {
+    let other = f64::from(DiamondStructure::Other as u8);
+    snapshot
+        .get_property("structure")
+        .iter()
+        .zip(snapshot.get_property("id"))
+        .filter(|(structure, _)| (**structure != other) == diamond)
+        .map(|(_, id)| *id as u64)
+        .collect()
+}
+
+/// Number of final atoms in every structure type
+fn get_structure_counts(snapshot: &DumpSnapshot) -> [usize; 7] {
+    let mut counts = [0; 7];
+    for structure in snapshot.get_property("structure") {
+        counts[*structure as usize] += 1;
+    }
+    counts
+}
+
+fn analyze_single_run(dir: &Path, cli: &Cli) -> Result<String> {
+    let dump_initial = DumpFile::read(&dir.join("dump.initial"), &[])?;
+    let snapshot_initial = dump_initial.get_snapshots()[0];
+    let zero_lvl = snapshot_initial.get_zero_lvl();
+    let dump_final = DumpFile::read(&dir.join(&cli.dump_final), &[])?;
+    let snapshot_final = dump_final.get_snapshots()[0];
+    let snapshot_initial = diamond_structure_snapshot(snapshot_initial, cli.search_radius);
+    let snapshot_final = diamond_structure_snapshot(snapshot_final, cli.search_radius);
+    let crystalline = get_ids(&snapshot_initial, true);
+    let amorphised = get_ids(&snapshot_final, false)
+        .into_iter()
+        .filter(|id| crystalline.contains(id))
+        .collect::<HashSet<_>>();
+    debug!("{}: {} amorphised atoms", dir.display(), amorphised.len());
+    let depths = snapshot_final
+        .get_property("id")
+        .iter()
+        .zip(snapshot_final.get_property("z"))
+        .filter(|(id, _)| amorphised.contains(&(**id as u64)))
+        .map(|(_, z)| zero_lvl - z)
+        .collect::<Vec<_>>();
+    let depth_avg = depths.iter().copied().avg().unwrap_or_default();
+    let depth_max = depths.iter().copied().fold(0.0, f64::max);
+    let volume = amorphised.len() as f64 * cli.atom_volume;
+    let counts = get_structure_counts(&snapshot_final);
+    if let Some(output) = &cli.output {
+        DumpFile::new(vec![snapshot_final]).save(&dir.join(output))?;
+    }
+    Ok(format!(
+        "{}\t{volume:10.4}\t{depth_avg:10.4}\t{depth_max:10.4}\t{}",
+        amorphised.len(),
+        counts.iter().join("\t")
+    ))
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    let header =
+        "amorphised volume depth_avg depth_max other cubic cubic_1 cubic_2 hex hex_1 hex_2";
+    let table = match &cli.command {
+        Commands::Single(args) => analyze_single_run(&args.run_dir, &cli)?,
+        Commands::Multi(args) => process_results_dir(&args.results_dir, args.threads, |dir| {
+            analyze_single_run(&dir.path, &cli)
+        })?
+        .into_iter()
+        .map(|(dir, info)| format!("{}\t{info}", dir.num))
+        .join("\n"),
+    };
+    let run_header = match cli.command {
+        Commands::Single(_) => "",
+        Commands::Multi(_) => "run ",
+    };
+    println!("# {run_header}{header}\n{table}");
+    Ok(())
+}
@@ -0,0 +1,277 @@
+use anyhow::{Context, Result, bail};
+use clap::{Args, Parser, Subcommand};
+use itertools::Itertools;
+use lammps_util_rust::{DumpFile, IteratorAvg, Trajectory, process_results_dir};
+use log::{debug, info};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Original depth and ejection time of the sputtered atoms
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    /// Atom types "<type 1>,<type 2>,...,<type N>", ex. "Si,C,O"
+    #[arg(short, long)]
+    particles: String,
+
+    /// Height of the escape plane above the zero level (A)
+    #[arg(short, long, default_value_t = 5.0)]
+    escape_height: f64,
+
+    /// Bin width of the depth distribution (A)
+    #[arg(short, long, default_value_t = 1.0)]
+    width: f64,
+
+    /// Number of bins of the ejection time distribution
+    #[arg(short = 'n', long, default_value_t = 50)]
+    time_bins: usize,
+
+    /// Time per step (ps), ejection times are in steps otherwise
+    #[arg(long)]
+    dt: Option<f64>,
+
+    /// File to write the depth and ejection step of every sputtered atom to
+    #[arg(short, long)]
+    atoms: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Escape depth and ejection time for a single run dir
+    Single(SingleCMD),
+
+    /// Escape depth and ejection time for the whole results folder
+    Multi(MultiCMD),
+}
+
+#[derive(Args)]
+struct SingleCMD {
+    run_dir: PathBuf,
+}
+
+#[derive(Args)]
+struct MultiCMD {
+    results_dir: PathBuf,
+
+    /// Number of threads to run in parallel
+    #[arg(short, long, default_value_t = 2)]
+    threads: usize,
+}
+
+struct SputteredAtom {
+    id: usize,
+    atom_type: usize,
+    /// Depth below the zero level in `dump.initial` (A)
+    depth: f64,
+    /// Last step the atom crossed the escape plane upwards and stayed above
+    step: Option<u64>,
+}
+
+struct Run {
+    atoms: Vec<SputteredAtom>,
+    first_step: u64,
+    last_step: u64,
+}
+
+fn read_dump(path: &Path) -> Result<DumpFile> {
+    DumpFile::read(path, &[]).context(format!("Failed to read {}", path.display()))
+}
+
+/// Step at which the heights stay above the plane for the rest of the steps,
+/// missing heights (NaN) are skipped
+fn get_ejection_step(steps: &[u64], heights: impl Iterator<Item = f64>, plane: f64) -> Option<u64> {
+    let mut ejection = None;
+    for (&step, z) in steps.iter().zip(heights).filter(|(_, z)| !z.is_nan()) {
+        if z <= plane {
+            ejection = None;
+        } else if ejection.is_none() {
+            ejection = Some(step);
+        }
+    }
+    ejection
+}
+
+fn process_run_dir(run_dir: &Path, escape_height: f64) -> Result<Run> {
+    let dump_sputter = read_dump(&run_dir.join("dump.sputter"))?;
+    let sputter = dump_sputter.get_snapshots()[0];
+    let ids = sputter
+        .get_property("id")
+        .iter()
+        .map(|&id| id as usize)
+        .collect::<Vec<_>>();
+    let types = sputter.get_property("type");
+    let dump_initial = read_dump(&run_dir.join("dump.initial"))?;
+    let initial = dump_initial.get_snapshots()[0];
+    let zero_lvl = initial.get_zero_lvl();
+    let initial_heights = initial
+        .get_property("id")
+        .iter()
+        .map(|&id| id as usize)
+        .zip(initial.get_property("z").iter().copied())
+        .collect::<HashMap<_, _>>();
+    let dump_during = read_dump(&run_dir.join("dump.during"))?;
+    let trajectory = Trajectory::with_ids(dump_during.get_snapshots(), &["x", "y", "z"], &ids);
+    let steps = trajectory.steps();
+    let plane = zero_lvl + escape_height;
+    let mut atoms = Vec::with_capacity(ids.len());
+    for (&id, &atom_type) in ids.iter().zip(types) {
+        // e.g. a reflected projectile has no initial position
+        let Some(z) = initial_heights.get(&id) else {
+            debug!("{}: atom {id} is not in dump.initial", run_dir.display());
+            continue;
+        };
+        let step = trajectory.get_atom_index(id).and_then(|atom_i| {
+            let unwrapped = trajectory.get_unwrapped_coordinates_by_index(atom_i);
+            get_ejection_step(steps, unwrapped.iter().map(|r| r[2]), plane)
+        });
+        atoms.push(SputteredAtom {
+            id,
+            atom_type: atom_type as usize,
+            depth: zero_lvl - z,
+            step,
+        });
+    }
+    info!(
+        "{}: {} of {} sputtered atoms crossed the escape plane",
+        run_dir.display(),
+        atoms.iter().filter(|a| a.step.is_some()).count(),
+        atoms.len()
+    );
+    Ok(Run {
+        atoms,
+        first_step: steps.first().copied().unwrap_or(0),
+        last_step: steps.last().copied().unwrap_or(0),
+    })
+}
+
+/// Counts per bin and type normalised per run, `bin` maps a value to its bin
+fn get_distribution(
+    values: impl Iterator<Item = (usize, f64)>,
+    bins: usize,
+    types: usize,
+    runs: usize,
+    bin: impl Fn(f64) -> usize,
+) -> Vec<Vec<f64>> {
+    let mut counts = vec![vec![0.0; types]; bins];
+    for (atom_type, value) in values.filter(|(t, _)| (1..=types).contains(t)) {
+        counts[bin(value).min(bins - 1)][atom_type - 1] += 1.0 / runs as f64;
+    }
+    counts
+}
+
+fn print_distributions(runs: &[(usize, Run)], cli: &Cli) -> Result<()> {
+    let type_names = cli.particles.split(',').map(str::trim).collect::<Vec<_>>();
+    let types = type_names.len();
+    let atoms = runs
+        .iter()
+        .flat_map(|(_, run)| &run.atoms)
+        .collect::<Vec<_>>();
+
+    let min_depth = atoms.iter().map(|a| a.depth).fold(0.0, f64::min);
+    let max_depth = atoms.iter().map(|a| a.depth).fold(0.0, f64::max);
+    let depth_lo = (min_depth / cli.width).floor() * cli.width;
+    let depth_bins = (((max_depth - depth_lo) / cli.width).floor() as usize + 1).max(1);
+    let depths = get_distribution(
+        atoms.iter().map(|a| (a.atom_type, a.depth)),
+        depth_bins,
+        types,
+        runs.len(),
+        |depth| ((depth - depth_lo) / cli.width) as usize,
+    );
+
+    let first_step = runs.iter().map(|(_, r)| r.first_step).min().unwrap_or(0);
+    let last_step = runs.iter().map(|(_, r)| r.last_step).max().unwrap_or(0);
+    let step_width = ((last_step - first_step) as f64 / cli.time_bins as f64).max(1.0);
+    let times = get_distribution(
+        atoms
+            .iter()
+            .filter_map(|a| a.step.map(|step| (a.atom_type, step as f64))),
+        cli.time_bins,
+        types,
+        runs.len(),
+        |step| ((step - first_step as f64) / step_width) as usize,
+    );
+    let time_scale = cli.dt.unwrap_or(1.0);
+
+    let row = |x: f64, counts: &[f64]| {
+        std::iter::once(x)
+            .chain(counts.iter().copied())
+            .map(|x| format!("{x:10.4}"))
+            .join("\t")
+    };
+    let depth_table = depths
+        .iter()
+        .enumerate()
+        .map(|(i, counts)| row((i as f64 + 0.5).mul_add(cli.width, depth_lo), counts))
+        .join("\n");
+    let time_table = times
+        .iter()
+        .enumerate()
+        .map(|(i, counts)| {
+            let step = (i as f64 + 0.5).mul_add(step_width, first_step as f64);
+            row(step * time_scale, counts)
+        })
+        .join("\n");
+    let summary = (1..=types)
+        .map(|t| {
+            let of_type = atoms
+                .iter()
+                .filter(|a| a.atom_type == t)
+                .collect::<Vec<_>>();
+            let depth = of_type.iter().map(|a| a.depth).avg().unwrap_or(0.0);
+            let escaped = of_type.iter().filter_map(|a| a.step).collect::<Vec<_>>();
+            let time = escaped.iter().map(|&s| s as f64 * time_scale).avg();
+            format!(
+                "{}\t{}\t{}\t{depth:10.4}\t{:10.4}",
+                type_names[t - 1],
+                of_type.len(),
+                escaped.len(),
+                time.unwrap_or(0.0)
+            )
+        })
+        .join("\n");
+
+    let names = type_names.join(" ");
+    let time_name = if cli.dt.is_some() { "time" } else { "step" };
+    println!("# type sputtered escaped depth {time_name}\n{summary}\n");
+    println!("# depth {names}\n{depth_table}\n");
+    println!("# {time_name} {names}\n{time_table}");
+
+    if let Some(path) = &cli.atoms {
+        let table = runs
+            .iter()
+            .flat_map(|(num, run)| {
+                run.atoms.iter().map(move |a| {
+                    let step = a.step.map_or("-".to_string(), |s| s.to_string());
+                    format!("{num}\t{}\t{}\t{:10.4}\t{step}", a.id, a.atom_type, a.depth)
+                })
+            })
+            .join("\n");
+        std::fs::write(path, format!("# run id type depth step\n{table}\n"))?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    if cli.time_bins == 0 {
+        bail!("The number of time bins must be positive");
+    }
+    if cli.width.is_nan() || cli.width <= 0.0 {
+        bail!("The depth bin width must be positive");
+    }
+    let runs = match &cli.command {
+        Commands::Single(args) => vec![(0, process_run_dir(&args.run_dir, cli.escape_height)?)],
+        Commands::Multi(args) => process_results_dir(&args.results_dir, args.threads, |dir| {
+            process_run_dir(&dir.path, cli.escape_height)
+        })?
+        .into_iter()
+        .map(|(dir, run)| (dir.num, run))
+        .collect(),
+    };
+    print_distributions(&runs, &cli)
+}
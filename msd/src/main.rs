@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use clap::{Args, Parser, Subcommand};
+use lammps_util_rust::{DumpFile, Trajectory, process_results_dir};
+use log::info;
+use rayon::prelude::*;
+use std::{
+    collections::{BTreeMap, btree_map::Entry},
+    fs, iter,
+    path::{Path, PathBuf},
+};
+
+/// Mean squared displacement of atoms in a trajectory
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    /// Dump file name inside a run dir
+    #[arg(short, long, default_value = "dump.during")]
+    dump_name: String,
+
+    /// File with whitespace separated ids of the selected atoms
+    #[arg(short, long)]
+    ids_file: Option<PathBuf>,
+
+    /// Width of the layers by initial depth below the surface (A)
+    #[arg(short, long)]
+    layer_width: Option<f64>,
+
+    /// Number of frames between time origins
+    #[arg(short, long, default_value_t = 1)]
+    origin_stride: usize,
+
+    /// Time per step (ps), adds a time column
+    #[arg(long)]
+    dt: Option<f64>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// MSD for a single run dir
+    Single(SingleCMD),
+
+    /// MSD averaged over the whole results folder
+    Multi(MultiCMD),
+}
+
+#[derive(Args)]
+struct SingleCMD {
+    run_dir: PathBuf,
+}
+
+#[derive(Args)]
+struct MultiCMD {
+    results_dir: PathBuf,
+
+    /// Number of threads to run in parallel
+    #[arg(short, long, default_value_t = 2)]
+    threads: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Accumulator {
+    count: usize,
+    sum: [f64; 3],
+    sum_sq: f64,
+}
+
+impl Accumulator {
+    fn push(&mut self, msd: [f64; 3]) {
+        self.count += 1;
+        iter::zip(&mut self.sum, msd).for_each(|(sum, msd)| *sum += msd);
+        self.sum_sq += msd.iter().sum::<f64>().powi(2);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.count += other.count;
+        iter::zip(&mut self.sum, other.sum).for_each(|(sum, other)| *sum += other);
+        self.sum_sq += other.sum_sq;
+    }
+
+    fn mean(&self) -> [f64; 3] {
+        self.sum.map(|sum| sum / self.count as f64)
+    }
+
+    fn std_err(&self) -> f64 {
+        let n = self.count as f64;
+        let mean = self.sum.iter().sum::<f64>() / n;
+        ((self.sum_sq / n - mean.powi(2)).max(0.0) / n).sqrt()
+    }
+}
+
+/// Per lag accumulators keyed by atom type and depth layer
+type Groups = BTreeMap<(usize, Option<i64>), Vec<Accumulator>>;
+
+fn merge_accumulators(a: &mut Vec<Accumulator>, b: &[Accumulator]) {
+    if b.len() > a.len() {
+        a.resize(b.len(), Accumulator::default());
+    }
+    iter::zip(a, b).for_each(|(a, b)| a.merge(b));
+}
+
+fn merge_groups(mut a: Groups, b: Groups) -> Groups {
+    for (key, accumulators) in b {
+        match a.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(accumulators);
+            }
+            Entry::Occupied(mut entry) => merge_accumulators(entry.get_mut(), &accumulators),
+        }
+    }
+    a
+}
+
+fn get_atom_msd(coords: &[[f64; 3]], origin_stride: usize) -> Vec<Option<[f64; 3]>> {
+    let n = coords.len();
+    (0..n)
+        .map(|lag| {
+            let (count, sum) = (0..n - lag)
+                .step_by(origin_stride)
+                .map(|t0| (coords[t0], coords[t0 + lag]))
+                .filter(|(a, b)| !a[0].is_nan() && !b[0].is_nan())
+                .fold((0, [0.0; 3]), |(count, mut sum), (a, b)| {
+                    (0..3).for_each(|d| sum[d] += (b[d] - a[d]).powi(2));
+                    (count + 1, sum)
+                });
+            (count > 0).then(|| sum.map(|sum| sum / f64::from(count)))
+        })
+        .collect()
+}
+
+fn first_value(series: &[f64]) -> Option<f64> {
+    series.iter().copied().find(|v| !v.is_nan())
+}
+
+fn get_msd(trajectory: &Trajectory, layer_width: Option<f64>, origin_stride: usize) -> Groups {
+    let zero_lvl = trajectory
+        .get_frame("z", 0)
+        .filter(|z| !z.is_nan())
+        .fold(f64::NEG_INFINITY, f64::max);
+    (0..trajectory.atoms_count())
+        .into_par_iter()
+        .fold(Groups::new, |mut groups, atom_i| {
+            let Some(atype) = first_value(trajectory.get_series_by_index("type", atom_i)) else {
+                return groups;
+            };
+            let layer = layer_width.and_then(|width| {
+                first_value(trajectory.get_series_by_index("z", atom_i))
+                    .map(|z| ((zero_lvl - z) / width).floor() as i64)
+            });
+            let coords = trajectory.get_unwrapped_coordinates_by_index(atom_i);
+            let accumulators = groups
+                .entry((atype as usize, layer))
+                .or_insert_with(|| vec![Accumulator::default(); coords.len()]);
+            iter::zip(accumulators, get_atom_msd(&coords, origin_stride))
+                .filter_map(|(acc, msd)| msd.map(|msd| (acc, msd)))
+                .for_each(|(acc, msd)| acc.push(msd));
+            groups
+        })
+        .reduce(Groups::new, merge_groups)
+}
+
+fn read_ids(path: &Path) -> Result<Vec<usize>> {
+    fs::read_to_string(path)
+        .with_context(|| format!("Failed to read ids from {}", path.display()))?
+        .split_whitespace()
+        .map(|id| id.parse::<usize>().context("Failed to parse atom id"))
+        .collect()
+}
+
+fn process_run(dir: &Path, cli: &Cli, ids: Option<&[usize]>) -> Result<(Vec<u64>, Groups)> {
+    let dump = DumpFile::read(&dir.join(&cli.dump_name), &[])?;
+    let snapshots = dump.get_snapshots();
+    let Some(first) = snapshots.first() else {
+        return Ok((Vec::new(), Groups::new()));
+    };
+    let mut keys = vec!["type", "x", "y", "z"];
+    if ["ix", "iy", "iz"]
+        .iter()
+        .all(|key| first.get_keys_map().contains_key(*key))
+    {
+        keys.extend(["ix", "iy", "iz"]);
+    }
+    let trajectory = match ids {
+        Some(ids) => Trajectory::with_ids(snapshots, &keys, ids),
+        None => Trajectory::new(snapshots, &keys),
+    };
+    info!(
+        "{}: {} atoms, {} steps",
+        dir.display(),
+        trajectory.atoms_count(),
+        trajectory.steps_count()
+    );
+    let lags = trajectory
+        .steps()
+        .iter()
+        .map(|step| step - trajectory.steps()[0])
+        .collect();
+    let groups = get_msd(&trajectory, cli.layer_width, cli.origin_stride.max(1));
+    Ok((lags, groups))
+}
+
+fn print_table(lags: &[u64], groups: &Groups, cli: &Cli) {
+    let layer_header = if cli.layer_width.is_some() {
+        " layer"
+    } else {
+        ""
+    };
+    let time_header = if cli.dt.is_some() { " time" } else { "" };
+    println!("# type{layer_header} step{time_header} msd_x msd_y msd_z msd σ(msd)");
+    for (&(atype, layer), accumulators) in groups {
+        for (lag, acc) in iter::zip(lags, accumulators).filter(|(_, acc)| acc.count > 0) {
+            let mut row = format!("{atype}");
+            if let Some(layer) = layer {
+                row.push_str(&format!("\t{layer}"));
+            }
+            row.push_str(&format!("\t{lag}"));
+            if let Some(dt) = cli.dt {
+                row.push_str(&format!("\t{:10.4}", *lag as f64 * dt));
+            }
+            let mean = acc.mean();
+            for value in [mean[0], mean[1], mean[2], mean.iter().sum(), acc.std_err()] {
+                row.push_str(&format!("\t{value:10.4}"));
+            }
+            println!("{row}");
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    let ids = cli.ids_file.as_deref().map(read_ids).transpose()?;
+    let ids = ids.as_deref();
+    let (lags, groups) = match &cli.command {
+        Commands::Single(args) => process_run(&args.run_dir, &cli, ids)?,
+        Commands::Multi(args) => process_results_dir(&args.results_dir, args.threads, |dir| {
+            process_run(&dir.path, &cli, ids)
+        })?
+        .into_iter()
+        .map(|(_, result)| result)
+        .fold(
+            (Vec::new(), Groups::new()),
+            |(lags, groups), (run_lags, run_groups)| {
+                let lags = if run_lags.len() > lags.len() {
+                    run_lags
+                } else {
+                    lags
+                };
+                (lags, merge_groups(groups, run_groups))
+            },
+        ),
+    };
+    print_table(&lags, &groups, &cli);
+    Ok(())
+}
@@ -1,111 +1,131 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::Parser;
 use itertools::Itertools;
-use lammps_util_rust::{DumpFile, DumpSnapshot, XYZ};
-use rayon::prelude::*;
-use std::{iter, path::PathBuf};
+use lammps_util_rust::{
+    Axis, DumpFile, DumpSnapshot, NeighborBackend, PairHistogram, get_slab_volume,
+};
+use log::info;
+use std::path::PathBuf;
 
+/// Total and partial radial distribution functions averaged over timesteps
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
     dump_file: PathBuf,
 
-    #[arg(short, long)]
-    timestep: Option<u64>,
+    /// Timesteps to average over, all by default
+    #[arg(short, long, value_delimiter = ',')]
+    timesteps: Vec<u64>,
 
     #[arg(short, long)]
-    cutoff: f32,
+    cutoff: f64,
 
     #[arg(short, long)]
     n_bins: usize,
-}
 
-fn get_bins(cutoff: f32, n: usize) -> Vec<(f32, f32)> {
-    let delta = cutoff / n as f32;
-    (0..n)
-        .map(|i| i as f32)
-        .map(|i| (i * delta, (i + 1.0) * delta))
-        .collect()
-}
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
 
-fn normalize(
-    rdf: impl IntoIterator<Item = ((f32, f32), usize)>,
-    dump: &DumpSnapshot,
-) -> Vec<(f32, f32)> {
-    let vol = dump.sym_box.volume();
-    let num = dump.atoms_count as f32;
-    let rho = num / vol;
-    rdf.into_iter()
-        .map(|((lo, hi), n)| {
-            let vshell = 4.0 / 3.0 * std::f32::consts::PI * (hi.powi(3) - lo.powi(3));
-            let nnorm = rho * vshell;
-            let n = n as f32 / (nnorm * num);
-            (hi.midpoint(lo), n)
-        })
-        .collect()
+    /// Only atoms between `zlo,zhi`, normalised by the volume of this slab
+    /// of the box, which leaves out the vacuum above a surface
+    #[arg(short, long, value_delimiter = ',', conflicts_with = "region")]
+    slab: Option<Vec<f64>>,
+
+    /// Only atoms inside `xlo,xhi,ylo,yhi,zlo,zhi`, normalised by its volume
+    #[arg(short, long, value_delimiter = ',')]
+    region: Option<Vec<f64>>,
 }
 
-fn get_rdf(cutoff: f32, n: usize, dump: &DumpSnapshot) -> Vec<(f32, f32)> {
-    let bins = get_bins(cutoff, n);
-    let mut coords = dump.get_coordinates();
-    // XYZ::get_supercell_coords(&mut coords, &dump.sym_box, cutoff);
-    println!("before tree");
-    let kdtree = kd_tree::KdTree::build_by_ordered_float(coords);
-    let rdf = kdtree
-        .items()
-        .par_iter()
-        .filter(|atom| atom.index < dump.atoms_count)
-        .map(|atom| {
-            let d_sq = kdtree
-                .within_radius(atom, cutoff)
+fn get_selection(snapshot: &DumpSnapshot, region: Option<&[f64; 6]>) -> Vec<bool> {
+    let Some(region) = region else {
+        return vec![true; snapshot.atoms_count];
+    };
+    let [x, y, z] = ["x", "y", "z"].map(|key| snapshot.get_property(key));
+    (0..snapshot.atoms_count)
+        .map(|i| {
+            [x[i], y[i], z[i]]
                 .into_iter()
-                .map(|neigh| atom.distance_squared(neigh.coords))
-                .collect::<Vec<_>>();
-            bins.iter()
-                .map(|&(lo, hi)| {
-                    let lo_sq = lo.powi(2);
-                    let hi_sq = hi.powi(2);
-                    let n = d_sq
-                        .iter()
-                        .filter(|&&d_sq| d_sq >= lo_sq && d_sq < hi_sq && d_sq != 0.0)
-                        .count();
-                    ((lo, hi), n)
-                })
-                .collect::<Vec<_>>()
+                .enumerate()
+                .all(|(d, c)| region[2 * d] <= c && c < region[2 * d + 1])
         })
-        .reduce(
-            || {
-                bins.iter()
-                    .map(|&(lo, hi)| ((lo, hi), 0))
-                    .collect::<Vec<_>>()
-            },
-            |mut a, b| {
-                iter::zip(&mut a, b).for_each(|(a, b)| a.1 += b.1);
-                a
-            },
-        );
-    normalize(rdf, dump)
+        .collect()
 }
 
 fn main() -> Result<()> {
     env_logger::init();
     let cli = Cli::parse();
-    let dump_path = cli.dump_file;
-    let timesteps = cli.timestep.map(|t| vec![t]).unwrap_or_default();
-    println!("before read dump");
-    let dump = DumpFile::read(dump_path.as_path(), &timesteps)?;
-    println!("read dump");
-    let snapshot = dump.get_snapshots()[0];
-    let rdf = get_rdf(cli.cutoff, cli.n_bins, snapshot);
-    let table = rdf
+    if cli.n_bins == 0 {
+        bail!("The number of bins must be positive");
+    }
+    let region = match &cli.region {
+        Some(region) => match <[f64; 6]>::try_from(region.as_slice()) {
+            Ok(region) => Some(region),
+            Err(_) => bail!("Expected xlo,xhi,ylo,yhi,zlo,zhi for the region"),
+        },
+        None => None,
+    };
+    let slab = match &cli.slab {
+        Some(slab) => match <[f64; 2]>::try_from(slab.as_slice()) {
+            Ok(slab) => Some(slab),
+            Err(_) => bail!("Expected zlo,zhi for the slab"),
+        },
+        None => None,
+    };
+    let dump = DumpFile::read(&cli.dump_file, &cli.timesteps)?;
+    let mut histogram = PairHistogram::new(cli.cutoff, cli.n_bins).with_backend(cli.backend);
+    for snapshot in dump.get_snapshots() {
+        let max_cutoff = snapshot.sym_box.max_cutoff();
+        if cli.cutoff > max_cutoff {
+            bail!(
+                "Step {}: the cutoff exceeds half of the periodic box, {max_cutoff}",
+                snapshot.step
+            );
+        }
+        let (volume, region) = match (region, slab) {
+            (Some(r), _) => ((r[1] - r[0]) * (r[3] - r[2]) * (r[5] - r[4]), Some(r)),
+            (None, Some([lo, hi])) => {
+                let volume = get_slab_volume(snapshot, Axis::Z, lo, hi);
+                let inf = f64::INFINITY;
+                (volume, Some([-inf, inf, -inf, inf, lo, hi]))
+            }
+            (None, None) => (f64::from(snapshot.sym_box.volume()), None),
+        };
+        let selected = get_selection(snapshot, region.as_ref());
+        histogram.add_snapshot(snapshot, volume, &selected);
+    }
+    if histogram.snapshots_count() == 0 {
+        bail!("No snapshots");
+    }
+    info!("{} snapshots averaged", histogram.snapshots_count());
+    let types = histogram.get_types();
+    let pairs = types
+        .iter()
+        .tuple_combinations()
+        .chain(types.iter().map(|t| (t, t)))
+        .sorted()
+        .collect::<Vec<_>>();
+    let partials = pairs
+        .iter()
+        .map(|&(a, b)| histogram.get_partial(*a, *b))
+        .collect::<Vec<_>>();
+    let total = histogram.get_total();
+    let table = histogram
+        .get_radii()
         .into_iter()
-        .map(|vals| {
-            <[_; 2]>::from(vals)
+        .enumerate()
+        .map(|(i, r)| {
+            [r, total[i]]
                 .into_iter()
+                .chain(partials.iter().map(|g| g[i]))
                 .map(|x| format!("{x:10.4}"))
                 .join("\t")
         })
         .join("\n");
-    println!("# radius number\n{table}");
+    let header = ["radius".to_string(), "g".to_string()]
+        .into_iter()
+        .chain(pairs.iter().map(|(a, b)| format!("g_{a}-{b}")))
+        .join(" ");
+    println!("# {header}\n{table}");
     Ok(())
 }
@@ -1,7 +1,8 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::{Parser, Subcommand};
 use lammps_util_rust::{
-    DumpFile, DumpSnapshot, clusterize_snapshot, copy_snapshot_with_indices, get_cluster_counts,
+    DumpFile, DumpSnapshot, NeighborBackend, PairCutoffs, SputterCriteria, SputterDetector,
+    copy_snapshot_with_indices,
 };
 use std::{
     fs::File,
@@ -15,6 +16,28 @@ use std::{
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Clustering cutoffs (A), a bare number or pairs like `1-1:3.0,1-2:2.5`
+    #[arg(short, long, default_value = "3.0")]
+    cutoffs: PairCutoffs,
+
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
+
+    /// Sputter criteria joined with `&` (all) or `|` (any) from `height>A`,
+    /// `vz>A/ps`, `energy>eV` and `size<N`, ex. "size<1000&height>2&vz>0"
+    #[arg(short, long, default_value = "size<1000")]
+    sputter: SputterCriteria,
+
+    /// Zero level for the height criterion
+    #[arg(short, long)]
+    zero_lvl: Option<f64>,
+
+    /// Masses of the types (amu) for the energy criterion when the dumps have
+    /// no `mass` column
+    #[arg(short, long, value_delimiter = ',')]
+    masses: Vec<f64>,
 }
 
 #[derive(Subcommand)]
@@ -45,31 +68,45 @@ struct DumpArgs {
     dump_final: PathBuf,
 }
 
-fn get_indices_to_delete(snapshot: &DumpSnapshot) -> Vec<usize> {
-    let snapshot = clusterize_snapshot(snapshot, 3.0);
-    let clusters_to_delete = get_cluster_counts(&snapshot)
+fn get_detector(cli: &Cli) -> Result<SputterDetector> {
+    let type_masses = [0.0]
         .into_iter()
-        .filter(|(_, count)| *count < 1000)
-        .map(|(id, _)| id)
-        .collect::<Vec<_>>();
-    snapshot
-        .get_property("cluster")
-        .iter()
-        .enumerate()
-        .filter(|(_, c)| clusters_to_delete.contains(&(**c as usize)))
-        .map(|(i, _)| i)
-        .collect()
+        .chain(cli.masses.iter().copied())
+        .collect();
+    let detector = SputterDetector::new(cli.cutoffs.clone(), cli.sputter.clone())
+        .with_backend(cli.backend)
+        .with_type_masses(type_masses);
+    match cli.zero_lvl {
+        Some(zero_lvl) => Ok(detector.with_zero_lvl(zero_lvl)),
+        None if cli.sputter.needs_zero_lvl() => bail!("The height criterion needs --zero-lvl"),
+        None => Ok(detector),
+    }
+}
+
+fn get_indices_to_delete(
+    snapshot: &DumpSnapshot,
+    detector: &SputterDetector,
+) -> Result<Vec<usize>> {
+    let missing = detector.get_missing_mass_types(snapshot);
+    if !missing.is_empty() {
+        bail!("No mass column and no --masses for the types {missing:?}");
+    }
+    let snapshot = detector.detect(snapshot);
+    let sputtered = snapshot.get_property("sputtered");
+    Ok((0..snapshot.atoms_count)
+        .filter(|&i| sputtered[i] > 0.0)
+        .collect())
 }
 
-fn get_ids_to_delete(snapshot: &DumpSnapshot) -> Vec<usize> {
-    let indices_to_delete = get_indices_to_delete(snapshot);
-    snapshot
+fn get_ids_to_delete(snapshot: &DumpSnapshot, detector: &SputterDetector) -> Result<Vec<usize>> {
+    let indices_to_delete = get_indices_to_delete(snapshot, detector)?;
+    Ok(snapshot
         .get_property("id")
         .iter()
         .enumerate()
         .filter(|(i, _)| indices_to_delete.contains(i))
         .map(|(_, id)| *id as usize)
-        .collect()
+        .collect())
 }
 
 fn delete_atoms(in_file: &Path, out_file: &Path, ids: &[usize]) -> Result<()> {
@@ -97,19 +134,19 @@ fn delete_atoms(in_file: &Path, out_file: &Path, ids: &[usize]) -> Result<()> {
     Ok(())
 }
 
-fn process_input(args: &InputArgs) -> Result<()> {
+fn process_input(args: &InputArgs, detector: &SputterDetector) -> Result<()> {
     let dump_final = DumpFile::read(&args.dump_final, &[])?;
-    let ids_to_delete = get_ids_to_delete(dump_final.get_snapshots()[0]);
+    let ids_to_delete = get_ids_to_delete(dump_final.get_snapshots()[0], detector)?;
     println!("about to delete {} atoms", ids_to_delete.len());
     delete_atoms(&args.input_file, &args.output_file, &ids_to_delete)?;
     println!("deleted {} atoms", ids_to_delete.len());
     Ok(())
 }
 
-fn process_dump(args: &DumpArgs) -> Result<()> {
+fn process_dump(args: &DumpArgs, detector: &SputterDetector) -> Result<()> {
     let dump_final = DumpFile::read(&args.dump_final, &[])?;
     let snapshot = dump_final.get_snapshots()[0];
-    let indices_to_delete = get_indices_to_delete(snapshot);
+    let indices_to_delete = get_indices_to_delete(snapshot, detector)?;
     println!("about to delete {} atoms", indices_to_delete.len());
     let indices_to_keep = (0..snapshot.atoms_count).filter(|i| !indices_to_delete.contains(i));
     let snapshot = copy_snapshot_with_indices(snapshot, indices_to_keep);
@@ -128,10 +165,11 @@ fn process_dump(args: &DumpArgs) -> Result<()> {
 fn main() -> Result<()> {
     env_logger::init();
     let cli = Cli::parse();
+    let detector = get_detector(&cli)?;
 
     match &cli.command {
-        Commands::Input(args) => process_input(args)?,
-        Commands::Dump(args) => process_dump(args)?,
+        Commands::Input(args) => process_input(args, &detector)?,
+        Commands::Dump(args) => process_dump(args, &detector)?,
     }
 
     Ok(())
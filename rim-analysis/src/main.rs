@@ -5,7 +5,7 @@ use geomutil_util::Point2;
 use itertools::{izip, Itertools};
 use lammps_util_rust::{
     clusterize_snapshot, copy_snapshot_with_indices, get_cluster_counts, process_results_dir,
-    DumpFile, DumpSnapshot, IteratorAvg,
+    DumpFile, DumpSnapshot, IteratorAvg, NeighborBackend, PairCutoffs,
 };
 use log::info;
 use std::{
@@ -27,9 +27,13 @@ type Sectors = [Sector; SECTORS_LEN];
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    /// Cutoff (A)
-    #[arg(short, long, default_value_t = 3.0)]
-    cutoff: f64,
+    /// Clustering cutoffs (A), a bare number or pairs like `1-1:3.0,1-2:2.5`
+    #[arg(short, long, default_value = "3.0")]
+    cutoffs: PairCutoffs,
+
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
 
     #[command(subcommand)]
     command: Commands,
@@ -134,11 +138,12 @@ fn get_above_zero(snap: &DumpSnapshot, zero_lvl: f64) -> DumpSnapshot {
 fn get_rim_snapshot(
     initial_snapshot: &DumpSnapshot,
     final_snapshot: &DumpSnapshot,
-    cutoff: f64,
+    cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
 ) -> DumpSnapshot {
     let zero_lvl = initial_snapshot.get_zero_lvl();
     let above_zero_lvl = get_above_zero(final_snapshot, zero_lvl);
-    let clusters = clusterize_snapshot(&above_zero_lvl, cutoff);
+    let clusters = clusterize_snapshot(&above_zero_lvl, cutoffs, backend);
     let clusters_selected = get_cluster_counts(&clusters)
         .iter()
         .filter(|(_, &cnt)| cnt >= RIM_THRESHOLD)
@@ -178,12 +183,16 @@ fn get_center_pos(path: &Path) -> Result<Point2> {
     Ok(Point2::from([x as f32, y as f32]))
 }
 
-fn get_rim_values(dir: &Path, cutoff: f64) -> Result<RimValues> {
+fn get_rim_values(
+    dir: &Path,
+    cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
+) -> Result<RimValues> {
     let dump_input = DumpFile::read(&dir.join("dump.initial"), &[])?;
     let snap_input = dump_input.get_snapshots()[0];
     let dump_final = DumpFile::read(&dir.join("dump.final_no_cluster"), &[])?;
     let snap_final = dump_final.get_snapshots()[0];
-    let snap_rim = get_rim_snapshot(snap_input, snap_final, cutoff);
+    let snap_rim = get_rim_snapshot(snap_input, snap_final, cutoffs, backend);
     let atoms = get_rim_atoms(&snap_rim);
     let dump_rim = DumpFile::new(vec![snap_rim]);
     dump_rim.save(&dir.join("dump.rim"))?;
@@ -193,39 +202,46 @@ fn get_rim_values(dir: &Path, cutoff: f64) -> Result<RimValues> {
     Ok(RimValues::new(atoms, center))
 }
 
-fn parse_run_dir(dir: &Path, cutoff: f64) -> Result<Sectors> {
-    let rim_values = get_rim_values(dir, cutoff)?;
+fn parse_run_dir(dir: &Path, cutoffs: &PairCutoffs, backend: NeighborBackend) -> Result<Sectors> {
+    let rim_values = get_rim_values(dir, cutoffs, backend)?;
     info!("rim count: {}", rim_values.atoms.len());
     Ok(rim_values.get_sectors())
 }
 
-fn run_single(dir: &Path, cutoff: f64) -> Result<Sectors> {
-    parse_run_dir(dir, cutoff)
-}
-
-fn run_multi(dir: &Path, threads: usize, cutoff: f64) -> Result<Sectors> {
-    Ok(
-        process_results_dir(dir, threads, |dir| parse_run_dir(&dir.path, cutoff))?
-            .into_iter()
-            .map(|(_, sectors)| sectors)
-            .reduce(|mut acc, sectors| {
-                zip(acc.iter_mut(), sectors).for_each(|(a, b)| {
-                    a.mass.extend(b.mass);
-                    a.radius.extend(b.radius);
-                    a.count.extend(b.count);
-                });
-                acc
-            })
-            .unwrap(),
-    )
+fn run_single(dir: &Path, cutoffs: &PairCutoffs, backend: NeighborBackend) -> Result<Sectors> {
+    parse_run_dir(dir, cutoffs, backend)
+}
+
+fn run_multi(
+    dir: &Path,
+    threads: usize,
+    cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
+) -> Result<Sectors> {
+    Ok(process_results_dir(dir, threads, |dir| {
+        parse_run_dir(&dir.path, cutoffs, backend)
+    })?
+    .into_iter()
+    .map(|(_, sectors)| sectors)
+    .reduce(|mut acc, sectors| {
+        zip(acc.iter_mut(), sectors).for_each(|(a, b)| {
+            a.mass.extend(b.mass);
+            a.radius.extend(b.radius);
+            a.count.extend(b.count);
+        });
+        acc
+    })
+    .unwrap())
 }
 
 fn main() -> Result<()> {
     env_logger::init();
     let cli = Cli::parse();
     let values = match cli.command {
-        Commands::Single(args) => run_single(&args.run_dir, cli.cutoff),
-        Commands::Multi(args) => run_multi(&args.results_dir, args.threads, cli.cutoff),
+        Commands::Single(args) => run_single(&args.run_dir, &cli.cutoffs, cli.backend),
+        Commands::Multi(args) => {
+            run_multi(&args.results_dir, args.threads, &cli.cutoffs, cli.backend)
+        }
     }?;
     let table = values
         .into_iter()
@@ -1,12 +1,16 @@
-use anyhow::Result;
-use clap::Parser;
-use lammps_util_rust::{DumpFile, DumpSnapshot, RunDir, get_runs_dirs};
-use rayon::{ThreadPoolBuilder, prelude::*};
-use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
+use anyhow::{Result, bail};
+use clap::{Parser, ValueEnum};
+use itertools::Itertools;
+use lammps_util_rust::{
+    ClusterProperties, DumpFile, RunDir, fit_cos_power, get_cluster_properties,
+    get_missing_mass_types, process_results_dir,
 };
+use log::warn;
+use std::f64::consts::PI;
+use std::path::PathBuf;
 
+/// Sputtering yield, cluster sizes, energy and angular distributions of the
+/// sputtered clusters over a results dir
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
@@ -16,138 +20,355 @@ struct Cli {
     #[arg(short, long)]
     particles: String,
 
+    /// Masses of the types (amu) when the dumps have no `mass` column
+    #[arg(short, long, value_delimiter = ',')]
+    masses: Vec<f64>,
+
+    /// Upper bound of the energy distributions (eV)
+    #[arg(short, long, default_value_t = 100.0)]
+    energy_max: f64,
+
+    /// Number of bins of the energy distributions
+    #[arg(short = 'n', long, default_value_t = 50)]
+    energy_bins: usize,
+
+    /// Bin width of the angular distributions (deg)
+    #[arg(short, long, default_value_t = 5.0)]
+    angle_step: f64,
+
+    /// Output format
+    #[arg(short, long, default_value = "text")]
+    format: Format,
+
+    /// Output file for text and json, stdout by default, or the directory for
+    /// the csv files, the results dir by default
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
     /// Number of threads to run in parallel
     #[arg(short, long, default_value_t = 2)]
     threads: usize,
 }
 
-struct Atom {
-    _id: usize,
-    atype: usize,
-    _coords: [f64; 3],
-    velocity: [f64; 3],
-    mass: f64,
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// Whitespace separated tables with `#` headers
+    Text,
+    /// One `<table>.csv` file per table
+    Csv,
+    /// One object with the columns and rows of every table
+    Json,
 }
 
-impl Atom {
-    fn new(id: usize, atype: usize, coords: [f64; 3], velocity: [f64; 3], mass: f64) -> Self {
+struct Table {
+    name: &'static str,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    fn new(name: &'static str, header: &str) -> Self {
         Self {
-            _id: id,
-            atype,
-            _coords: coords,
-            velocity,
-            mass,
+            name,
+            header: header.split(' ').map(str::to_string).collect(),
+            rows: Vec::new(),
         }
     }
-}
 
-#[derive(Default)]
-struct Cluster {
-    mass: f64,
-    counts: HashMap<usize, usize>,
-    momentum: [f64; 3],
-    ek: f64,
-    angle: f64,
-}
+    fn with_header(name: &'static str, header: Vec<String>) -> Self {
+        Self {
+            name,
+            header,
+            rows: Vec::new(),
+        }
+    }
 
-impl Cluster {
-    fn new(atoms: &[Atom]) -> Self {
-        let mut cluster = Cluster::default();
-        for atom in atoms {
-            cluster.mass += atom.mass;
-            for (i, vel) in atom.velocity.iter().enumerate() {
-                cluster.momentum[i] += vel * atom.mass;
+    fn push(&mut self, row: impl IntoIterator<Item = String>) {
+        self.rows.push(row.into_iter().collect());
+    }
+
+    fn to_text(&self) -> String {
+        let rows = self.rows.iter().map(|row| row.join("\t"));
+        [
+            format!("# {}", self.name),
+            format!("# {}", self.header.join(" ")),
+        ]
+        .into_iter()
+        .chain(rows)
+        .join("\n")
+    }
+
+    fn to_csv(&self) -> String {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| row.iter().map(|c| c.trim()).join(","));
+        std::iter::once(self.header.join(","))
+            .chain(rows)
+            .join("\n")
+            + "\n"
+    }
+
+    fn to_json(&self) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        // numbers are written as they are, anything else as a string
+        let cell = |c: &String| {
+            let c = c.trim();
+            if c.parse::<f64>().is_ok_and(f64::is_finite) {
+                c.to_string()
+            } else {
+                quote(c)
             }
-            cluster
-                .counts
-                .entry(atom.atype)
-                .and_modify(|n| *n += 1)
-                .or_default();
-        }
-        cluster.ek = 2.0
-            * 5.1875
-            * 1e-5
-            * (cluster.momentum[0].powi(2)
-                + cluster.momentum[1].powi(2)
-                + cluster.momentum[2].powi(2))
-            / (2.0 * cluster.mass);
-        cluster.angle = (cluster.momentum[2]
-            / (cluster.momentum[0].powi(2) + cluster.momentum[1].powi(2)).sqrt())
-        .atan();
-        cluster
+        };
+        let columns = self.header.iter().map(|c| quote(c)).join(", ");
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| format!("[{}]", row.iter().map(cell).join(", ")))
+            .join(",\n      ");
+        format!(
+            "{}: {{\n    \"columns\": [{columns}],\n    \"rows\": [\n      {rows}\n    ]\n  }}",
+            quote(self.name)
+        )
     }
 }
 
 fn parse_types(s: &str) -> Vec<String> {
-    s.split(":").map(|s| s.trim().to_string()).collect()
-}
-
-fn get_clusters(dump: &DumpSnapshot) -> Vec<Cluster> {
-    let id = dump.get_property("id");
-    let atype = dump.get_property("type");
-    let x = dump.get_property("x");
-    let y = dump.get_property("y");
-    let z = dump.get_property("z");
-    let vx = dump.get_property("vx");
-    let vy = dump.get_property("vy");
-    let vz = dump.get_property("vz");
-    let mass = dump.get_property("mass");
-    let cluster = dump.get_property("cluster");
-    let mut clusters = HashMap::new();
-    for i in 0..dump.atoms_count {
-        clusters
-            .entry(cluster[i] as usize)
-            .and_modify(|v: &mut Vec<_>| {
-                v.push(Atom::new(
-                    id[i] as usize,
-                    atype[i] as usize,
-                    [x[i], y[i], z[i]],
-                    [vx[i], vy[i], vz[i]],
-                    mass[i],
-                ));
+    s.split(',').map(|s| s.trim().to_string()).collect()
+}
+
+fn fmt(x: f64) -> String {
+    format!("{x:10.4}")
+}
+
+/// Counts of the values in `n` equal bins of `[min, max)`
+fn histogram(values: impl Iterator<Item = f64>, min: f64, max: f64, n: usize) -> Vec<usize> {
+    let mut counts = vec![0; n];
+    let width = (max - min) / n as f64;
+    for value in values.filter(|v| (min..max).contains(v)) {
+        counts[(((value - min) / width) as usize).min(n - 1)] += 1;
+    }
+    counts
+}
+
+/// Mean and its standard error
+fn mean_with_error(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    if values.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, (variance / n).sqrt())
+}
+
+fn yield_table(results: &[(RunDir, Vec<ClusterProperties>)], type_names: &[String]) -> Table {
+    let mut table = Table::new("yield", "type yield error");
+    let per_run = |atom_type: Option<usize>| {
+        results
+            .iter()
+            .map(|(_, clusters)| {
+                clusters
+                    .iter()
+                    .flat_map(|c| &c.composition)
+                    .filter(|(t, _)| atom_type.is_none_or(|atom_type| **t == atom_type))
+                    .map(|(_, n)| *n as f64)
+                    .sum::<f64>()
             })
-            .or_insert(Vec::new());
+            .collect::<Vec<_>>()
+    };
+    let rows = type_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.clone(), per_run(Some(i + 1))))
+        .chain(std::iter::once(("all".to_string(), per_run(None))));
+    for (name, yields) in rows {
+        let (mean, error) = mean_with_error(&yields);
+        table.push([name, fmt(mean), fmt(error)]);
     }
-    clusters.values().map(|atoms| Cluster::new(atoms)).collect()
+    table
 }
 
-fn do_single_dir(dir: RunDir) -> Result<(usize, Vec<Cluster>)> {
-    let dump = DumpFile::read(&dir.path.join("dump.sputter"), &[])?;
-    let clusters = get_clusters(dump.get_snapshots()[0]);
-    Ok((dir.num, clusters))
+fn sizes_table(clusters: &[&ClusterProperties], runs: f64) -> Table {
+    let mut table = Table::new("sizes", "size clusters per_run");
+    let counts = clusters.iter().map(|c| c.atoms_count()).counts();
+    for (size, count) in counts.into_iter().sorted() {
+        table.push([
+            size.to_string(),
+            count.to_string(),
+            fmt(count as f64 / runs),
+        ]);
+    }
+    table
 }
 
-fn do_results_dir(results_dir: &Path, threads: usize) -> Result<Vec<(usize, Vec<Cluster>)>> {
-    let tp = ThreadPoolBuilder::new().num_threads(threads).build()?;
-    let run_dirs = get_runs_dirs(results_dir)?;
-    let mut results = tp.install(|| {
-        run_dirs
-            .into_par_iter()
-            .map(do_single_dir)
-            .collect::<Result<Vec<_>>>()
-    })?;
-    results.sort_by(|a, b| a.0.cmp(&b.0));
-    Ok(results)
+/// Energy distributions per run of the single atoms and of the clusters of
+/// two atoms and more
+fn energy_table(clusters: &[&ClusterProperties], runs: f64, cli: &Cli) -> Table {
+    let mut table = Table::new("energy", "energy atoms clusters");
+    let energies = |monomers: bool| {
+        let values = clusters
+            .iter()
+            .filter(|c| (c.atoms_count() == 1) == monomers)
+            .map(|c| c.translational_energy);
+        histogram(values, 0.0, cli.energy_max, cli.energy_bins)
+    };
+    let width = cli.energy_max / cli.energy_bins as f64;
+    for (i, (atoms, multimers)) in energies(true).into_iter().zip(energies(false)).enumerate() {
+        table.push([
+            fmt((i as f64 + 0.5) * width),
+            fmt(atoms as f64 / runs / width),
+            fmt(multimers as f64 / runs / width),
+        ]);
+    }
+    table
+}
+
+/// Polar distribution per run and solid angle with its `a cos^n` fit
+fn polar_tables(clusters: &[&ClusterProperties], runs: f64, step: f64) -> Vec<Table> {
+    let n = (90.0 / step).ceil() as usize;
+    let counts = histogram(
+        clusters.iter().map(|c| c.polar_angle()),
+        0.0,
+        n as f64 * step,
+        n,
+    );
+    let angles = (0..n).map(|i| (i as f64 + 0.5) * step).collect::<Vec<_>>();
+    let densities = counts
+        .iter()
+        .enumerate()
+        .map(|(i, &count)| {
+            let [lo, hi] = [i, i + 1].map(|j| (j as f64 * step).to_radians().cos());
+            count as f64 / runs / (2.0 * PI * (lo - hi))
+        })
+        .collect::<Vec<_>>();
+    let weights = counts.iter().map(|&c| c as f64).collect::<Vec<_>>();
+    let fit = fit_cos_power(&angles, &densities, &weights);
+    let mut polar = Table::new("polar", "angle counts dN/dOmega fit");
+    for ((angle, count), density) in angles.iter().zip(&counts).zip(&densities) {
+        let fitted = fit.map_or(0.0, |(a, n)| a * angle.to_radians().cos().powf(n));
+        polar.push([fmt(*angle), count.to_string(), fmt(*density), fmt(fitted)]);
+    }
+    let mut tables = vec![polar];
+    match fit {
+        Some((a, n)) => {
+            let mut fit = Table::new("polar_fit", "a n");
+            fit.push([fmt(a), fmt(n)]);
+            tables.push(fit);
+        }
+        None => warn!("Too few emission angles to fit cos^n"),
+    }
+    tables
+}
+
+fn azimuth_table(clusters: &[&ClusterProperties], runs: f64, step: f64) -> Table {
+    let n = (360.0 / step).ceil() as usize;
+    let azimuths = clusters.iter().map(|c| c.azimuthal_angle());
+    let counts = histogram(azimuths, -180.0, n as f64 * step - 180.0, n);
+    let mut table = Table::new("azimuth", "angle counts per_run");
+    for (i, count) in counts.into_iter().enumerate() {
+        table.push([
+            fmt((i as f64 + 0.5).mul_add(step, -180.0)),
+            count.to_string(),
+            fmt(count as f64 / runs),
+        ]);
+    }
+    table
+}
+
+fn clusters_table(results: &[(RunDir, Vec<ClusterProperties>)], type_names: &[String]) -> Table {
+    let header = ["run"]
+        .into_iter()
+        .chain(type_names.iter().map(String::as_str))
+        .chain(["∑", "mass", "ek", "ek_internal", "angle", "azimuth", "rg"])
+        .map(str::to_string)
+        .collect();
+    let mut table = Table::with_header("clusters", header);
+    for (dir, clusters) in results {
+        for cluster in clusters {
+            let counts = (1..=type_names.len())
+                .map(|t| cluster.composition.get(&t).copied().unwrap_or(0))
+                .chain([cluster.atoms_count()])
+                .map(|n| n.to_string());
+            let values = [
+                cluster.mass,
+                cluster.translational_energy,
+                cluster.internal_energy,
+                cluster.polar_angle(),
+                cluster.azimuthal_angle(),
+                cluster.radius_of_gyration,
+            ]
+            .map(fmt);
+            table.push(
+                std::iter::once(dir.num.to_string())
+                    .chain(counts)
+                    .chain(values),
+            );
+        }
+    }
+    table
+}
+
+fn write_tables(tables: &[Table], cli: &Cli) -> Result<()> {
+    let output = match cli.format {
+        Format::Text => tables.iter().map(Table::to_text).join("\n\n") + "\n",
+        Format::Json => format!(
+            "{{\n  {}\n}}\n",
+            tables.iter().map(Table::to_json).join(",\n  ")
+        ),
+        Format::Csv => {
+            let dir = cli.output.as_ref().unwrap_or(&cli.results_dir);
+            for table in tables {
+                std::fs::write(dir.join(format!("{}.csv", table.name)), table.to_csv())?;
+            }
+            return Ok(());
+        }
+    };
+    match &cli.output {
+        Some(path) => std::fs::write(path, output)?,
+        None => print!("{output}"),
+    }
+    Ok(())
 }
 
 fn main() -> Result<()> {
     env_logger::init();
     let cli = Cli::parse();
+    if cli.energy_bins == 0 {
+        bail!("The number of energy bins must be positive");
+    }
+    if !(cli.angle_step > 0.0 && cli.angle_step <= 90.0) {
+        bail!("The angle step must be within (0, 90] deg");
+    }
     let type_names = parse_types(&cli.particles);
-    let clusters = do_results_dir(&cli.results_dir, cli.threads)?;
-    println!("# № {} ∑", type_names.join(" "));
-    for (sim_num, clusters) in clusters.into_iter() {
-        for cluster in clusters.into_iter() {
-            let sum = cluster.counts.values().copied().sum::<usize>();
-            let mut counts = cluster.counts.iter().collect::<Vec<_>>();
-            counts.sort_by(|a, b| a.0.cmp(b.0));
-            let counts_s = counts
-                .into_iter()
-                .map(|(_, c)| c.to_string())
-                .collect::<Vec<_>>()
-                .join("\n");
-            println!("{sim_num} {} {sum}", counts_s);
+    let type_masses = [0.0]
+        .into_iter()
+        .chain(cli.masses.iter().copied())
+        .collect::<Vec<_>>();
+    let results = process_results_dir(&cli.results_dir, cli.threads, |dir| {
+        let dump = DumpFile::read(&dir.path.join("dump.sputter"), &[])?;
+        let snapshot = dump.get_snapshots()[0];
+        let missing = get_missing_mass_types(snapshot, &type_masses);
+        if !missing.is_empty() {
+            bail!(
+                "{}: no mass column and no --masses for the types {missing:?}",
+                dir.path.display()
+            );
         }
+        Ok(get_cluster_properties(snapshot, &type_masses))
+    })?;
+    if results.is_empty() {
+        bail!("No runs in {}", cli.results_dir.display());
     }
-    Ok(())
+    let runs = results.len() as f64;
+    let clusters = results.iter().flat_map(|(_, c)| c).collect::<Vec<_>>();
+    let mut tables = vec![
+        yield_table(&results, &type_names),
+        sizes_table(&clusters, runs),
+        energy_table(&clusters, runs, &cli),
+    ];
+    tables.extend(polar_tables(&clusters, runs, cli.angle_step));
+    tables.push(azimuth_table(&clusters, runs, cli.angle_step));
+    tables.push(clusters_table(&results, &type_names));
+    write_tables(&tables, &cli)
 }
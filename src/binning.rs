@@ -0,0 +1,305 @@
+use std::{collections::BTreeMap, f64::consts::PI, str::FromStr};
+
+use crate::DumpSnapshot;
+
+/// Cartesian axis, parsed from `x`, `y` or `z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    #[must_use] pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    #[must_use] pub const fn name(self) -> &'static str {
+        match self {
+            Self::X => "x",
+            Self::Y => "y",
+            Self::Z => "z",
+        }
+    }
+
+    /// The two other axes, in cyclic order.
+    const fn get_normal_axes(self) -> (usize, usize) {
+        let i = self.index();
+        ((i + 1) % 3, (i + 2) % 3)
+    }
+}
+
+impl FromStr for Axis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "x" => Ok(Self::X),
+            "y" => Ok(Self::Y),
+            "z" => Ok(Self::Z),
+            _ => Err(format!("unknown axis: {s}")),
+        }
+    }
+}
+
+/// Spatial bins atoms are sorted into, like the bin styles of LAMMPS
+/// `compute chunk/atom`. Bins are indexed by `floor(coordinate / width)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binning {
+    /// Slabs, columns or boxes along up to three axes with their widths
+    Cartesian(Vec<(Axis, f64)>),
+    /// Shells around the `axis` line through `center`, optionally also
+    /// sliced along the axis
+    Cylindrical {
+        axis: Axis,
+        center: [f64; 2],
+        radial_width: f64,
+        axial_width: Option<f64>,
+    },
+    /// Shells around `center`
+    Spherical { center: [f64; 3], width: f64 },
+}
+
+/// Index of a bin, one entry per binned coordinate.
+pub type BinIndex = Vec<i64>;
+
+impl Binning {
+    /// Names of the binned coordinates.
+    #[must_use] pub fn get_coordinate_names(&self) -> Vec<&'static str> {
+        match self {
+            Self::Cartesian(axes) => axes.iter().map(|(axis, _)| axis.name()).collect(),
+            Self::Cylindrical {
+                axis, axial_width, ..
+            } => std::iter::once("r")
+                .chain(axial_width.map(|_| axis.name()))
+                .collect(),
+            Self::Spherical { .. } => vec!["r"],
+        }
+    }
+
+    /// Bin of a position.
+    #[must_use] pub fn get_bin(&self, position: [f64; 3]) -> BinIndex {
+        let index = |value: f64, width: f64| (value / width).floor() as i64;
+        match self {
+            Self::Cartesian(axes) => axes
+                .iter()
+                .map(|(axis, width)| index(position[axis.index()], *width))
+                .collect(),
+            Self::Cylindrical {
+                axis,
+                center,
+                radial_width,
+                axial_width,
+            } => {
+                let (u, v) = axis.get_normal_axes();
+                let r = (position[u] - center[0]).hypot(position[v] - center[1]);
+                std::iter::once(index(r, *radial_width))
+                    .chain(axial_width.map(|width| index(position[axis.index()], width)))
+                    .collect()
+            }
+            Self::Spherical { center, width } => {
+                let r = (0..3)
+                    .map(|d| (position[d] - center[d]).powi(2))
+                    .sum::<f64>()
+                    .sqrt();
+                vec![index(r, *width)]
+            }
+        }
+    }
+
+    /// Centre of the bin along every binned coordinate.
+    #[must_use] pub fn get_center(&self, bin: &[i64]) -> Vec<f64> {
+        let widths = match self {
+            Self::Cartesian(axes) => axes.iter().map(|(_, width)| *width).collect(),
+            Self::Cylindrical {
+                radial_width,
+                axial_width,
+                ..
+            } => std::iter::once(*radial_width).chain(*axial_width).collect(),
+            Self::Spherical { width, .. } => vec![*width],
+        };
+        bin.iter()
+            .zip(widths)
+            .map(|(&i, width)| (i as f64 + 0.5) * width)
+            .collect()
+    }
+
+    /// Volume of the bin, unbinned directions span the box `extents`.
+    #[must_use] pub fn get_volume(&self, bin: &[i64], extents: [f64; 3]) -> f64 {
+        match self {
+            Self::Cartesian(axes) => (0..3)
+                .map(|d| {
+                    axes.iter()
+                        .find(|(axis, _)| axis.index() == d)
+                        .map_or(extents[d], |(_, width)| *width)
+                })
+                .product(),
+            Self::Cylindrical {
+                axis,
+                radial_width,
+                axial_width,
+                ..
+            } => {
+                let (r1, r2) = (bin[0] as f64 * radial_width, (bin[0] + 1) as f64 * radial_width);
+                PI * (r2 * r2 - r1 * r1) * axial_width.unwrap_or(extents[axis.index()])
+            }
+            Self::Spherical { width, .. } => {
+                let (r1, r2) = (bin[0] as f64 * width, (bin[0] + 1) as f64 * width);
+                4.0 / 3.0 * PI * (r2.powi(3) - r1.powi(3))
+            }
+        }
+    }
+}
+
+/// Accumulated values of the atoms in a bin.
+#[derive(Debug, Clone, Default)]
+pub struct BinStats {
+    pub count: usize,
+    pub sums: Vec<f64>,
+    pub sums_sq: Vec<f64>,
+}
+
+/// Counts, sums, means and densities of per-atom columns in spatial bins,
+/// averaged over all added snapshots like LAMMPS `fix ave/chunk`.
+pub struct BinAccumulator {
+    binning: Binning,
+    keys: Vec<String>,
+    bins: BTreeMap<BinIndex, BinStats>,
+    snapshots_count: usize,
+    extents: [f64; 3],
+}
+
+impl BinAccumulator {
+    #[must_use] pub fn new(binning: Binning, keys: &[&str]) -> Self {
+        Self {
+            binning,
+            keys: keys.iter().map(ToString::to_string).collect(),
+            bins: BTreeMap::new(),
+            snapshots_count: 0,
+            extents: [0.0; 3],
+        }
+    }
+
+    pub fn add_snapshot(&mut self, snapshot: &DumpSnapshot) {
+        let dimensions = snapshot.sym_box.dimensions::<f64>();
+        let n = self.snapshots_count as f64;
+        for d in 0..3 {
+            self.extents[d] = (self.extents[d] * n + dimensions[d]) / (n + 1.0);
+        }
+        self.snapshots_count += 1;
+        let [x, y, z] = ["x", "y", "z"].map(|key| snapshot.get_property(key));
+        let columns = self
+            .keys
+            .iter()
+            .map(|key| snapshot.get_property(key))
+            .collect::<Vec<_>>();
+        for i in 0..snapshot.atoms_count {
+            let bin = self.binning.get_bin([x[i], y[i], z[i]]);
+            let stats = self.bins.entry(bin).or_insert_with(|| BinStats {
+                count: 0,
+                sums: vec![0.0; columns.len()],
+                sums_sq: vec![0.0; columns.len()],
+            });
+            stats.count += 1;
+            for (j, column) in columns.iter().enumerate() {
+                stats.sums[j] += column[i];
+                stats.sums_sq[j] += column[i] * column[i];
+            }
+        }
+    }
+
+    #[must_use] pub const fn binning(&self) -> &Binning {
+        &self.binning
+    }
+
+    #[must_use] pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    #[must_use] pub const fn snapshots_count(&self) -> usize {
+        self.snapshots_count
+    }
+
+    /// Non-empty bins in index order.
+    pub fn bins(&self) -> impl Iterator<Item = (&BinIndex, &BinStats)> {
+        self.bins.iter()
+    }
+
+    /// Average number of atoms in the bin per snapshot.
+    #[must_use] pub fn get_count(&self, stats: &BinStats) -> f64 {
+        stats.count as f64 / self.snapshots_count.max(1) as f64
+    }
+
+    /// Volume of the bin, unbinned directions span the average box.
+    #[must_use] pub fn get_volume(&self, bin: &[i64]) -> f64 {
+        self.binning.get_volume(bin, self.extents)
+    }
+
+    /// Average number density of the bin.
+    #[must_use] pub fn get_density(&self, bin: &[i64], stats: &BinStats) -> f64 {
+        self.get_count(stats) / self.get_volume(bin)
+    }
+
+    /// Average sum of the column `key_j` in the bin per snapshot.
+    #[must_use] pub fn get_sum(&self, stats: &BinStats, key_j: usize) -> f64 {
+        stats.sums[key_j] / self.snapshots_count.max(1) as f64
+    }
+
+    /// Mean and standard deviation of the column `key_j` over the atoms.
+    #[must_use] pub fn get_mean(&self, stats: &BinStats, key_j: usize) -> (f64, f64) {
+        let n = stats.count.max(1) as f64;
+        let mean = stats.sums[key_j] / n;
+        let std = (stats.sums_sq[key_j] / n - mean * mean).max(0.0).sqrt();
+        (mean, std)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use assert_float_eq::assert_f64_near;
+
+    #[test]
+    fn test_binning() {
+        let snapshot = test_util::snapshot(
+            0,
+            "pp pp ss",
+            4.0,
+            &[
+                ("x", &[0.5, 1.5, 3.5, 3.0]),
+                ("y", &[0.0, 0.0, 0.0, 0.0]),
+                ("z", &[0.5, 1.5, 1.2, 3.9]),
+                ("c_pe", &[1.0, 2.0, 4.0, 8.0]),
+            ],
+        );
+        let binning = Binning::Cartesian(vec![(Axis::Z, 2.0)]);
+        let mut accumulator = BinAccumulator::new(binning, &["c_pe"]);
+        accumulator.add_snapshot(&snapshot);
+        accumulator.add_snapshot(&snapshot);
+        let bins = accumulator.bins().collect::<Vec<_>>();
+        assert_eq!(bins.len(), 2);
+        let (bin, stats) = bins[0];
+        assert_eq!(bin, &[0]);
+        assert_f64_near!(accumulator.get_count(stats), 3.0);
+        assert_f64_near!(accumulator.get_density(bin, stats), 3.0 / 32.0);
+        assert_f64_near!(accumulator.get_sum(stats, 0), 7.0);
+        assert_f64_near!(accumulator.get_mean(stats, 0).0, 7.0 / 3.0);
+        assert_eq!(accumulator.binning().get_center(bins[1].0), &[3.0]);
+
+        let binning = Binning::Cylindrical {
+            axis: Axis::Z,
+            center: [0.0, 0.0],
+            radial_width: 1.0,
+            axial_width: None,
+        };
+        assert_eq!(binning.get_bin([1.5, 1.5, 9.0]), &[2]);
+        assert_f64_near!(binning.get_volume(&[1], [4.0; 3]), 12.0 * PI);
+        let binning = Binning::Spherical {
+            center: [1.0; 3],
+            width: 1.0,
+        };
+        assert_eq!(binning.get_bin([1.0, 1.0, -0.5]), &[1]);
+    }
+}
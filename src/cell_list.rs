@@ -0,0 +1,133 @@
+use rayon::prelude::*;
+
+use crate::{NeighborSearch, Real, Vector3};
+
+/// Linked-cell (binned) spatial index for fixed radius queries.
+///
+/// Points are sorted by the cell they fall into, the cell edge is at least
+/// the cutoff so a query only visits the adjacent cells. The edge grows for
+/// sparse snapshots to keep the number of cells close to the number of points.
+pub struct CellList<T = f64> {
+    lo: Vector3<T>,
+    cell_size: T,
+    dims: [usize; 3],
+    cell_starts: Vec<usize>,
+    points: Vec<(usize, Vector3<T>)>,
+}
+
+impl<T: Real> CellList<T> {
+    fn get_cell(&self, point: &Vector3<T>) -> [i64; 3] {
+        std::array::from_fn(|i| ((point[i] - self.lo[i]) / self.cell_size).floor().to_i64())
+    }
+
+    fn get_cell_index(&self, cell: [usize; 3]) -> usize {
+        (cell[0] * self.dims[1] + cell[1]) * self.dims[2] + cell[2]
+    }
+
+    fn get_cell_points(&self, cell: [usize; 3]) -> &[(usize, Vector3<T>)] {
+        let i = self.get_cell_index(cell);
+        &self.points[self.cell_starts[i]..self.cell_starts[i + 1]]
+    }
+}
+
+impl<T: Real> NeighborSearch<T> for CellList<T> {
+    fn build(points: Vec<Vector3<T>>, cutoff: T) -> Self {
+        let (lo, hi) = points.iter().fold(
+            ([T::INFINITY; 3], [T::NEG_INFINITY; 3]),
+            |(mut lo, mut hi), point| {
+                for i in 0..3 {
+                    lo[i] = lo[i].min(point[i]);
+                    hi[i] = hi[i].max(point[i]);
+                }
+                (lo, hi)
+            },
+        );
+        let extent: [T; 3] = std::array::from_fn(|i| (hi[i] - lo[i]).max(T::ZERO));
+        let volume = extent[0] * extent[1] * extent[2];
+        let sparse_size = (volume / T::from_usize(points.len().max(1))).cbrt();
+        let cell_size = cutoff.max(sparse_size).max(T::EPSILON);
+        let dims = extent.map(|e| (e / cell_size).floor().to_i64() as usize + 1);
+        let mut cell_list = Self {
+            lo: if points.is_empty() {
+                Vector3::zero()
+            } else {
+                Vector3::from(lo)
+            },
+            cell_size,
+            dims,
+            cell_starts: vec![0; dims.iter().product::<usize>() + 1],
+            points: Vec::new(),
+        };
+        let mut cells = points
+            .into_par_iter()
+            .enumerate()
+            .map(|(index, point)| {
+                let cell = cell_list.get_cell(&point);
+                let cell = std::array::from_fn(|i| (cell[i].max(0) as usize).min(dims[i] - 1));
+                (cell_list.get_cell_index(cell), (index, point))
+            })
+            .collect::<Vec<_>>();
+        cells.par_sort_unstable_by_key(|(cell, (index, _))| (*cell, *index));
+        for (cell, _) in &cells {
+            cell_list.cell_starts[cell + 1] += 1;
+        }
+        for i in 1..cell_list.cell_starts.len() {
+            cell_list.cell_starts[i] += cell_list.cell_starts[i - 1];
+        }
+        cell_list.points = cells.into_iter().map(|(_, point)| point).collect();
+        cell_list
+    }
+
+    fn within_radius(&self, point: &Vector3<T>, radius: T) -> Vec<usize> {
+        let cell = self.get_cell(point);
+        let reach = (radius / self.cell_size).ceil().to_i64();
+        let ranges: [_; 3] = std::array::from_fn(|i| {
+            let lo = (cell[i] - reach).max(0);
+            let hi = (cell[i] + reach).min(self.dims[i] as i64 - 1);
+            lo..=hi
+        });
+        let radius_sq = radius * radius;
+        let mut result = Vec::new();
+        for cx in ranges[0].clone() {
+            for cy in ranges[1].clone() {
+                for cz in ranges[2].clone() {
+                    let cell = [cx as usize, cy as usize, cz as usize];
+                    result.extend(
+                        self.get_cell_points(cell)
+                            .iter()
+                            .filter(|(_, p)| p.distance_squared(*point) <= radius_sq)
+                            .map(|(index, _)| *index),
+                    );
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KdTreeSearch;
+
+    #[test]
+    fn test_cell_list_matches_kd_tree() {
+        let mut seed = 12345u64;
+        let mut random = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let points = (0..500)
+            .map(|_| Vector3::new(random() * 20.0, random() * 20.0, random() * 60.0))
+            .collect::<Vec<_>>();
+        let cell_list = CellList::build(points.clone(), 2.5);
+        let kd_tree = <KdTreeSearch as NeighborSearch<f64>>::build(points.clone(), 2.5);
+        for point in &points {
+            let mut a = cell_list.within_radius(point, 2.5);
+            let mut b = kd_tree.within_radius(point, 2.5);
+            a.sort_unstable();
+            b.sort_unstable();
+            assert_eq!(a, b);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+
+use crate::{DumpSnapshot, Vector3};
+
+/// Converts `m v^2` from amu (A/ps)^2 (LAMMPS metal units) to eV.
+pub const MVV_TO_EV: f64 = 1.036_426_9e-4;
+
+/// Properties of one cluster of a clustered snapshot.
+///
+/// Positions are unwrapped around the first atom of the cluster along the
+/// periodic axes, energies are in eV and angles in degrees.
+#[derive(Debug, Clone)]
+pub struct ClusterProperties {
+    /// Label of the cluster in the `cluster` column
+    pub id: usize,
+    /// Number of atoms per atom type
+    pub composition: BTreeMap<usize, usize>,
+    pub mass: f64,
+    pub center_of_mass: Vector3<f64>,
+    /// Velocity of the centre of mass
+    pub velocity: Vector3<f64>,
+    /// Kinetic energy of the centre of mass motion
+    pub translational_energy: f64,
+    /// Kinetic energy of the rotation and vibration about the centre of mass
+    pub internal_energy: f64,
+    pub radius_of_gyration: f64,
+    /// Mass-weighted gyration tensor in the order `xx, yy, zz, xy, xz, yz`
+    pub gyration_tensor: [f64; 6],
+}
+
+impl ClusterProperties {
+    #[must_use] pub fn atoms_count(&self) -> usize {
+        self.composition.values().sum()
+    }
+
+    /// Angle between the centre of mass velocity and the surface normal +z.
+    #[must_use] pub fn polar_angle(&self) -> f64 {
+        let speed = self.velocity.length();
+        if speed == 0.0 {
+            return 0.0;
+        }
+        (self.velocity.z / speed).clamp(-1.0, 1.0).acos().to_degrees()
+    }
+
+    /// Direction of the centre of mass velocity in the xy plane from +x.
+    #[must_use] pub fn azimuthal_angle(&self) -> f64 {
+        self.velocity.y.atan2(self.velocity.x).to_degrees()
+    }
+}
+
+/// Sorted atom types of the snapshot without a mass, none when it has the
+/// `mass` column, otherwise the types beyond the end of `type_masses`.
+#[must_use] pub fn get_missing_mass_types(snapshot: &DumpSnapshot, type_masses: &[f64]) -> Vec<usize> {
+    if snapshot.get_keys_map().contains_key("mass") {
+        return Vec::new();
+    }
+    let mut types = snapshot
+        .get_property("type")
+        .iter()
+        .map(|&t| t as usize)
+        .filter(|&t| t >= type_masses.len())
+        .collect::<Vec<_>>();
+    types.sort_unstable();
+    types.dedup();
+    types
+}
+
+/// Properties of every cluster of a snapshot with the `cluster` column,
+/// sorted by cluster id.
+///
+/// Masses are read from the `mass` column, or from `type_masses` indexed by
+/// atom type when the snapshot has none.
+///
+/// # Panics
+///
+/// If the snapshot lacks the `cluster`, position or velocity columns, or
+/// [`get_missing_mass_types`] is not empty.
+#[must_use] pub fn get_cluster_properties(snapshot: &DumpSnapshot, type_masses: &[f64]) -> Vec<ClusterProperties> {
+    let clusters = snapshot.get_property("cluster");
+    let types = snapshot.get_property("type");
+    let masses = if snapshot.get_keys_map().contains_key("mass") {
+        snapshot.get_property("mass").to_vec()
+    } else {
+        types.iter().map(|&t| type_masses[t as usize]).collect()
+    };
+    let positions = snapshot.get_positions::<f64>();
+    let [vx, vy, vz] = ["vx", "vy", "vz"].map(|key| snapshot.get_property(key));
+    let dimensions = snapshot.sym_box.dimensions::<f64>();
+    let periodic = snapshot.sym_box.periodic();
+    let mut members = BTreeMap::<usize, Vec<usize>>::new();
+    for (i, &cluster) in clusters.iter().enumerate() {
+        members.entry(cluster as usize).or_default().push(i);
+    }
+    members
+        .into_iter()
+        .map(|(id, atoms)| {
+            let origin = positions[atoms[0]];
+            let unwrapped = atoms
+                .iter()
+                .map(|&i| {
+                    let mut delta = positions[i] - origin;
+                    for d in (0..3).filter(|&d| periodic[d]) {
+                        delta[d] -= (delta[d] / dimensions[d]).round() * dimensions[d];
+                    }
+                    origin + delta
+                })
+                .collect::<Vec<_>>();
+            let velocities = atoms
+                .iter()
+                .map(|&i| Vector3::new(vx[i], vy[i], vz[i]))
+                .collect::<Vec<_>>();
+            let atom_masses = atoms.iter().map(|&i| masses[i]).collect::<Vec<_>>();
+            let mass = atom_masses.iter().sum::<f64>();
+            let weighted_sum = |vectors: &[Vector3<f64>]| {
+                vectors
+                    .iter()
+                    .zip(&atom_masses)
+                    .fold(Vector3::zero(), |sum, (v, m)| sum + *v * *m)
+                    / mass
+            };
+            let center_of_mass = weighted_sum(&unwrapped);
+            let velocity = weighted_sum(&velocities);
+            let total_energy = velocities
+                .iter()
+                .zip(&atom_masses)
+                .map(|(v, m)| m * v.length_squared())
+                .sum::<f64>()
+                * MVV_TO_EV
+                / 2.0;
+            let translational_energy = mass * velocity.length_squared() * MVV_TO_EV / 2.0;
+            let mut gyration_tensor = [0.0; 6];
+            for (r, m) in unwrapped.iter().zip(&atom_masses) {
+                let r = *r - center_of_mass;
+                let products = [r.x * r.x, r.y * r.y, r.z * r.z, r.x * r.y, r.x * r.z, r.y * r.z];
+                for (g, p) in gyration_tensor.iter_mut().zip(products) {
+                    *g += m * p / mass;
+                }
+            }
+            let mut composition = BTreeMap::new();
+            for &i in &atoms {
+                *composition.entry(types[i] as usize).or_default() += 1;
+            }
+            ClusterProperties {
+                id,
+                composition,
+                mass,
+                center_of_mass,
+                velocity,
+                translational_energy,
+                internal_energy: (total_energy - translational_energy).max(0.0),
+                radius_of_gyration: gyration_tensor[..3].iter().sum::<f64>().sqrt(),
+                gyration_tensor,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use assert_float_eq::assert_f64_near;
+
+    #[test]
+    fn test_cluster_properties() {
+        let snapshot = test_util::snapshot(
+            0,
+            "pp pp ss",
+            10.0,
+            &[
+                ("type", &[1.0, 2.0, 1.0]),
+                ("cluster", &[1.0, 1.0, 3.0]),
+                ("x", &[9.5, 0.5, 5.0]),
+                ("y", &[5.0, 5.0, 5.0]),
+                ("z", &[5.0, 5.0, 5.0]),
+                ("vx", &[1.0, -1.0, 1.0]),
+                ("vy", &[0.0, 0.0, 0.0]),
+                ("vz", &[1.0, 1.0, 1.0]),
+            ],
+        );
+        let clusters = get_cluster_properties(&snapshot, &[0.0, 2.0, 2.0]);
+        assert_eq!(clusters.len(), 2);
+        let dimer = &clusters[0];
+        assert_eq!(dimer.composition, BTreeMap::from([(1, 1), (2, 1)]));
+        assert_f64_near!(dimer.mass, 4.0);
+        // unwrapped through the periodic x boundary
+        assert_f64_near!(dimer.center_of_mass.x, 10.0);
+        assert_f64_near!(dimer.radius_of_gyration, 0.5);
+        assert_f64_near!(dimer.translational_energy, 2.0 * MVV_TO_EV);
+        assert_f64_near!(dimer.internal_energy, 2.0 * MVV_TO_EV);
+        assert_f64_near!(dimer.polar_angle(), 0.0);
+        assert_f64_near!(clusters[1].polar_angle(), 45.0, 8);
+        assert_eq!(get_missing_mass_types(&snapshot, &[0.0, 2.0]), [2]);
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{DumpSnapshot, UnionFind};
+
+/// What happened to the clusters of one connected group of the overlap graph
+/// between two consecutive snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClusterEventKind {
+    /// A cluster without atoms in the previous snapshot
+    Appearance,
+    /// One cluster split into several
+    Fragmentation,
+    /// Several clusters joined into one
+    Merge,
+    /// Several clusters exchanged atoms and ended up as several others
+    Exchange,
+    /// A cluster whose atoms are all gone from the snapshot
+    Disappearance,
+}
+
+impl ClusterEventKind {
+    #[must_use] pub const fn name(self) -> &'static str {
+        match self {
+            Self::Appearance => "appearance",
+            Self::Fragmentation => "fragmentation",
+            Self::Merge => "merge",
+            Self::Exchange => "exchange",
+            Self::Disappearance => "disappearance",
+        }
+    }
+}
+
+/// Change of the tracked clusters at a timestep, `parents` and `children` are
+/// pairs of persistent cluster id and atoms count.
+#[derive(Debug, Clone)]
+pub struct ClusterEvent {
+    pub timestep: u64,
+    pub kind: ClusterEventKind,
+    pub parents: Vec<(usize, usize)>,
+    pub children: Vec<(usize, usize)>,
+}
+
+/// Follows the clusters of consecutive clustered snapshots and gives them
+/// persistent ids.
+///
+/// A cluster keeps the id of the previous cluster it shares the most atoms
+/// with, every id goes to one cluster at most, the rest get new ids. Any
+/// cluster that does not just continue a single previous one is recorded as
+/// a [`ClusterEvent`].
+#[derive(Default)]
+pub struct ClusterTracker {
+    next_id: usize,
+    /// Atom ids of the current clusters by persistent id
+    clusters: BTreeMap<usize, BTreeSet<usize>>,
+    events: Vec<ClusterEvent>,
+    snapshots_count: usize,
+}
+
+impl ClusterTracker {
+    #[must_use] pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Matches the clusters of the snapshot with the `id` and `cluster`
+    /// columns to the tracked ones.
+    ///
+    /// # Panics
+    ///
+    /// If the snapshot lacks the `id` or `cluster` columns.
+    pub fn add_snapshot(&mut self, snapshot: &DumpSnapshot) {
+        let ids = snapshot.get_property("id");
+        let labels = snapshot.get_property("cluster");
+        let mut current = BTreeMap::<usize, BTreeSet<usize>>::new();
+        for (&id, &label) in ids.iter().zip(labels) {
+            current.entry(label as usize).or_default().insert(id as usize);
+        }
+        let current = current.into_values().collect::<Vec<_>>();
+        let previous = std::mem::take(&mut self.clusters);
+        let previous_ids = previous.keys().copied().collect::<Vec<_>>();
+        let owners = previous
+            .iter()
+            .flat_map(|(&p, atoms)| atoms.iter().map(move |&atom| (atom, p)))
+            .collect::<BTreeMap<_, _>>();
+
+        // nodes are previous clusters first and then the current ones
+        let mut sets = UnionFind::new(previous_ids.len() + current.len());
+        let mut overlaps = BTreeMap::<(usize, usize), usize>::new();
+        for (c, atoms) in current.iter().enumerate() {
+            for atom in atoms {
+                if let Some(&p) = owners.get(atom) {
+                    *overlaps.entry((previous_ids.binary_search(&p).unwrap(), c)).or_default() += 1;
+                }
+            }
+        }
+        for &(p, c) in overlaps.keys() {
+            sets.union(p, previous_ids.len() + c);
+        }
+
+        // every id goes to the current cluster it overlaps the most
+        let mut new_ids = vec![None; current.len()];
+        let mut taken = vec![false; previous_ids.len()];
+        let mut matches = overlaps.iter().collect::<Vec<_>>();
+        matches.sort_by_key(|&(&(p, c), &n)| (std::cmp::Reverse(n), previous_ids[p], c));
+        for (&(p, c), _) in matches {
+            if !taken[p] && new_ids[c].is_none() {
+                taken[p] = true;
+                new_ids[c] = Some(previous_ids[p]);
+            }
+        }
+        let new_ids = new_ids
+            .into_iter()
+            .map(|id| {
+                id.unwrap_or_else(|| {
+                    self.next_id += 1;
+                    self.next_id
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut groups = BTreeMap::<usize, (Vec<(usize, usize)>, Vec<(usize, usize)>)>::new();
+        for (p, &id) in previous_ids.iter().enumerate() {
+            groups.entry(sets.find(p)).or_default().0.push((id, previous[&id].len()));
+        }
+        for (c, &id) in new_ids.iter().enumerate() {
+            let root = sets.find(previous_ids.len() + c);
+            groups.entry(root).or_default().1.push((id, current[c].len()));
+        }
+        if self.snapshots_count > 0 {
+            for (parents, children) in groups.into_values() {
+                let kind = match (parents.len(), children.len()) {
+                    (1, 1) => continue,
+                    (0, _) => ClusterEventKind::Appearance,
+                    (_, 0) => ClusterEventKind::Disappearance,
+                    (1, _) => ClusterEventKind::Fragmentation,
+                    (_, 1) => ClusterEventKind::Merge,
+                    _ => ClusterEventKind::Exchange,
+                };
+                self.events.push(ClusterEvent {
+                    timestep: snapshot.step,
+                    kind,
+                    parents,
+                    children,
+                });
+            }
+        }
+        self.clusters = new_ids.into_iter().zip(current).collect();
+        self.snapshots_count += 1;
+    }
+
+    /// Atom ids of the clusters of the last snapshot by persistent id.
+    #[must_use] pub const fn clusters(&self) -> &BTreeMap<usize, BTreeSet<usize>> {
+        &self.clusters
+    }
+
+    /// Events in the order of the snapshots.
+    #[must_use] pub fn events(&self) -> &[ClusterEvent] {
+        &self.events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn clusters_snapshot(step: u64, clusters: &[f64]) -> DumpSnapshot {
+        let ids = (1..=clusters.len()).map(|id| id as f64).collect::<Vec<_>>();
+        test_util::snapshot(
+            step,
+            "pp pp pp",
+            10.0,
+            &[("id", &ids), ("cluster", clusters)],
+        )
+    }
+
+    #[test]
+    fn test_cluster_tracking() {
+        let mut tracker = ClusterTracker::new();
+        tracker.add_snapshot(&clusters_snapshot(0, &[1.0, 1.0, 1.0, 1.0, 5.0]));
+        assert!(tracker.events().is_empty());
+        let first = tracker.clusters().keys().copied().collect::<Vec<_>>();
+        assert_eq!(first.len(), 2);
+
+        // the first cluster loses a monomer, the labels change too
+        tracker.add_snapshot(&clusters_snapshot(10, &[2.0, 2.0, 2.0, 4.0, 5.0]));
+        let events = tracker.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, ClusterEventKind::Fragmentation);
+        assert_eq!(events[0].timestep, 10);
+        assert_eq!(events[0].parents, vec![(first[0], 4)]);
+        assert_eq!(events[0].children[0], (first[0], 3));
+        let monomer = events[0].children[1].0;
+        assert!(!first.contains(&monomer));
+        assert_eq!(tracker.clusters()[&first[1]], BTreeSet::from([5]));
+
+        tracker.add_snapshot(&clusters_snapshot(20, &[1.0, 1.0, 1.0, 4.0, 4.0]));
+        let event = &tracker.events()[1];
+        assert_eq!(event.kind, ClusterEventKind::Merge);
+        assert_eq!(event.parents, vec![(first[1], 1), (monomer, 1)]);
+        assert_eq!(event.children, vec![(first[1], 2)]);
+        assert_eq!(tracker.clusters().len(), 2);
+    }
+}
@@ -1,49 +1,82 @@
 use log::debug;
 
-use crate::{copy_snapshot_with_keys, DumpSnapshot, XYZ};
-use std::collections::{HashMap, HashSet};
+use crate::{copy_snapshot_with_keys, DumpSnapshot, NeighborBackend, NeighborList, PairCutoffs};
+use std::collections::HashMap;
 
-#[must_use] pub fn clusterize_snapshot(snapshot: &DumpSnapshot, cutoff: f64) -> DumpSnapshot {
-    assert!(cutoff >= 0.0);
-    let mut snapshot = copy_snapshot_with_keys(snapshot, ["cluster"].into_iter());
-    let coords = snapshot.get_coordinates();
-    let clusters = clusterize_coords(&coords, cutoff);
-    let cluster_j = snapshot.get_property_index("cluster");
-    let id_j = snapshot.get_property_index("id");
-    for atom_i in 0..snapshot.atoms_count {
-        let (&cluster_i, _) = clusters
-            .iter()
-            .find(|(_, cluster)| cluster.contains(&atom_i))
-            .unwrap();
-        let cluster_id = snapshot.get_atom_value(id_j, cluster_i);
-        snapshot.set_atom_value(cluster_j, atom_i, cluster_id);
-    }
-    snapshot
+/// Disjoint sets of atom indices with path halving and union by size.
+pub struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
 }
 
-fn clusterize_coords(coords: &Vec<XYZ>, cutoff: f64) -> HashMap<usize, HashSet<usize>> {
-    let kdtree = kd_tree::KdTree::build_by_ordered_float(coords.clone());
-    let mut visited = vec![false; coords.len()];
-    let mut map = HashMap::new();
-    for atom in coords {
-        if visited[atom.index] {
-            continue;
+impl UnionFind {
+    #[must_use] pub fn new(n: usize) -> Self {
+        Self {
+            parents: (0..n).collect(),
+            sizes: vec![1; n],
         }
-        visited[atom.index] = true;
-        let cluster = map.entry(atom.index).or_insert(HashSet::new());
-        let mut stack = vec![atom];
-        while let Some(atom) = stack.pop() {
-            cluster.insert(atom.index);
-            for neigh in kdtree.within_radius(atom, cutoff as f32) {
-                if !visited[neigh.index] {
-                    visited[neigh.index] = true;
-                    stack.push(neigh);
-                }
-            }
+    }
+
+    /// Representative of the set of `i`.
+    pub fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
         }
+        i
+    }
+
+    /// Merges the sets of `a` and `b`.
+    pub fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.sizes[a] < self.sizes[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+    }
+
+    /// Size of the set of `i`.
+    pub fn size(&mut self, i: usize) -> usize {
+        let root = self.find(i);
+        self.sizes[root]
+    }
+}
+
+/// Copy of the snapshot with the `cluster` column, atoms closer than the
+/// cutoff of their type pair (through periodic boundaries too) share a
+/// cluster labelled with the smallest atom id in it.
+#[must_use] pub fn clusterize_snapshot(
+    snapshot: &DumpSnapshot,
+    cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
+) -> DumpSnapshot {
+    assert!(cutoffs.max_cutoff() >= 0.0);
+    let neighbors = NeighborList::new_with_cutoffs(snapshot, cutoffs, backend);
+    clusterize_neighbors(snapshot, &neighbors)
+}
+
+fn clusterize_neighbors(snapshot: &DumpSnapshot, neighbors: &NeighborList) -> DumpSnapshot {
+    let mut sets = UnionFind::new(neighbors.len());
+    for (atom_i, neigh) in neighbors.pairs() {
+        sets.union(atom_i, neigh.index);
+    }
+    let ids = snapshot.get_property("id");
+    let mut cluster_ids = vec![f64::INFINITY; neighbors.len()];
+    for (atom_i, &id) in ids.iter().enumerate() {
+        let root = sets.find(atom_i);
+        cluster_ids[root] = cluster_ids[root].min(id);
+    }
+    let mut snapshot = copy_snapshot_with_keys(snapshot, ["cluster"].into_iter());
+    let cluster_j = snapshot.get_property_index("cluster");
+    for atom_i in 0..snapshot.atoms_count {
+        let root = sets.find(atom_i);
+        snapshot.set_atom_value(cluster_j, atom_i, cluster_ids[root]);
     }
-    debug!("clusters {map:?}");
-    map
+    snapshot
 }
 
 #[must_use] pub fn get_cluster_counts(snapshot: &DumpSnapshot) -> HashMap<usize, usize> {
@@ -65,3 +98,39 @@ fn clusterize_coords(coords: &Vec<XYZ>, cutoff: f64) -> HashMap<usize, HashSet<u
         .expect("Cluster snapshot is empty");
     max_cluster
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn test_clusterize() {
+        let snapshot = test_util::snapshot(
+            0,
+            "pp ss ss",
+            10.0,
+            &[
+                ("id", &[7.0, 3.0, 5.0, 9.0, 4.0]),
+                ("type", &[1.0, 1.0, 1.0, 1.0, 2.0]),
+                ("x", &[0.5, 9.5, 5.0, 5.0, 6.5]),
+                ("y", &[1.0, 1.0, 5.0, 9.0, 5.0]),
+                ("z", &[1.0, 1.0, 5.0, 9.0, 5.0]),
+            ],
+        );
+        let cutoffs = PairCutoffs::new(2.0);
+        let clusters = clusterize_snapshot(&snapshot, &cutoffs, NeighborBackend::KdTree);
+        // the first two are bonded through the periodic x boundary
+        assert_eq!(clusters.get_property("cluster"), &[3.0, 3.0, 4.0, 9.0, 4.0]);
+        let counts = get_cluster_counts(&clusters);
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts[&4], 2);
+
+        let clusters = clusterize_snapshot(&snapshot, &cutoffs, NeighborBackend::CellList);
+        assert_eq!(clusters.get_property("cluster"), &[3.0, 3.0, 4.0, 9.0, 4.0]);
+
+        let cutoffs = PairCutoffs::new(2.0).with_pair(1, 2, 1.0);
+        let clusters = clusterize_snapshot(&snapshot, &cutoffs, NeighborBackend::KdTree);
+        assert_eq!(clusters.get_property("cluster"), &[3.0, 3.0, 5.0, 9.0, 4.0]);
+    }
+}
@@ -0,0 +1,141 @@
+use rayon::prelude::*;
+
+use crate::{copy_snapshot_with_keys, DumpSnapshot, NeighborBackend, NeighborList, PairCutoffs};
+
+/// Coordination numbers of the atoms in a snapshot, split by neighbour type.
+///
+/// Two atoms are neighbours when they are closer than the cutoff of their
+/// type pair in [`PairCutoffs`].
+pub struct Coordination {
+    types: Vec<usize>,
+    atom_types: Vec<usize>,
+    counts: Vec<usize>,
+}
+
+impl Coordination {
+    #[must_use] pub fn new(snapshot: &DumpSnapshot, cutoffs: &PairCutoffs, backend: NeighborBackend) -> Self {
+        let neighbors = NeighborList::<f64>::new_with_cutoffs(snapshot, cutoffs, backend);
+        Self::from_neighbors(snapshot, &neighbors)
+    }
+
+    /// Counts the neighbours of an already built list, the snapshot is only
+    /// used for the atom types.
+    #[must_use] pub fn from_neighbors(snapshot: &DumpSnapshot, neighbors: &NeighborList) -> Self {
+        let atom_types = snapshot
+            .get_property("type")
+            .iter()
+            .map(|&t| t as usize)
+            .collect::<Vec<_>>();
+        let mut types = atom_types.clone();
+        types.sort_unstable();
+        types.dedup();
+        let counts = (0..neighbors.len())
+            .into_par_iter()
+            .flat_map_iter(|i| {
+                let mut counts = vec![0; types.len()];
+                for neigh in neighbors.neighbors(i) {
+                    let type_i = types.binary_search(&atom_types[neigh.index]).unwrap();
+                    counts[type_i] += 1;
+                }
+                counts
+            })
+            .collect();
+        Self {
+            types,
+            atom_types,
+            counts,
+        }
+    }
+
+    /// Sorted atom types present in the snapshot.
+    #[must_use] pub fn types(&self) -> &[usize] {
+        &self.types
+    }
+
+    #[must_use] pub fn atoms_count(&self) -> usize {
+        self.atom_types.len()
+    }
+
+    #[must_use] pub fn get_atom_type(&self, atom_i: usize) -> usize {
+        self.atom_types[atom_i]
+    }
+
+    /// Neighbour counts of the atom, one per entry of [`Coordination::types`].
+    #[must_use] pub fn get_partials(&self, atom_i: usize) -> &[usize] {
+        let n = self.types.len();
+        &self.counts[atom_i * n..(atom_i + 1) * n]
+    }
+
+    /// Number of neighbours of type `neighbor_type`, zero for absent types.
+    #[must_use] pub fn get_partial(&self, atom_i: usize, neighbor_type: usize) -> usize {
+        self.types
+            .binary_search(&neighbor_type)
+            .map_or(0, |type_i| self.get_partials(atom_i)[type_i])
+    }
+
+    #[must_use] pub fn get_total(&self, atom_i: usize) -> usize {
+        self.get_partials(atom_i).iter().sum()
+    }
+}
+
+/// Copy of the snapshot with the `coordination` column and a
+/// `coordination_<type>` column for every atom type present, `coordination`
+/// must come from the same snapshot.
+#[must_use] pub fn coordination_snapshot(snapshot: &DumpSnapshot, coordination: &Coordination) -> DumpSnapshot {
+    let partial_keys = coordination
+        .types()
+        .iter()
+        .map(|t| format!("coordination_{t}"))
+        .collect::<Vec<_>>();
+    let mut snapshot = copy_snapshot_with_keys(
+        snapshot,
+        ["coordination"]
+            .into_iter()
+            .chain(partial_keys.iter().map(String::as_str)),
+    );
+    let total = snapshot.get_property_mut("coordination");
+    for (atom_i, value) in total.iter_mut().enumerate() {
+        *value = coordination.get_total(atom_i) as f64;
+    }
+    for (type_i, key) in partial_keys.iter().enumerate() {
+        let partial = snapshot.get_property_mut(key);
+        for (atom_i, value) in partial.iter_mut().enumerate() {
+            *value = coordination.get_partials(atom_i)[type_i] as f64;
+        }
+    }
+    snapshot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn test_coordination() {
+        let snapshot = test_util::snapshot(
+            0,
+            "pp pp ss",
+            10.0,
+            &[
+                ("id", &[1.0, 2.0, 3.0, 4.0]),
+                ("type", &[1.0, 1.0, 2.0, 2.0]),
+                ("x", &[0.5, 9.0, 2.0, 5.0]),
+                ("y", &[5.0, 5.0, 5.0, 5.0]),
+                ("z", &[5.0, 5.0, 5.0, 5.0]),
+            ],
+        );
+        let cutoffs = "1-1:1.6,1-2:1.6".parse::<PairCutoffs>().unwrap();
+        let coordination = Coordination::new(&snapshot, &cutoffs, NeighborBackend::KdTree);
+        assert_eq!(coordination.types(), &[1, 2]);
+        assert_eq!(coordination.get_partials(0), &[1, 1]);
+        assert_eq!(coordination.get_total(1), 1);
+        assert_eq!(coordination.get_partial(2, 1), 1);
+        assert_eq!(coordination.get_total(3), 0);
+        assert_eq!(coordination.get_partial(0, 3), 0);
+
+        let snapshot = coordination_snapshot(&snapshot, &coordination);
+        assert_eq!(snapshot.get_property("coordination"), &[2.0, 1.0, 1.0, 0.0]);
+        assert_eq!(snapshot.get_property("coordination_2"), &[1.0, 0.0, 0.0, 0.0]);
+    }
+}
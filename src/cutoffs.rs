@@ -0,0 +1,114 @@
+use std::{collections::HashMap, str::FromStr};
+
+/// Cutoff radii for pairs of atom types.
+///
+/// Parsed from a comma separated list like `1-1:2.6,1-2:2.1,2-2:1.9`, an
+/// entry without a pair (`3.0` or `*:3.0`) sets the cutoff of every pair that
+/// is not listed. Pairs are symmetric, `1-2` and `2-1` are the same pair.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PairCutoffs {
+    default: Option<f64>,
+    pairs: HashMap<(usize, usize), f64>,
+}
+
+const fn pair_key(a: usize, b: usize) -> (usize, usize) {
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+impl PairCutoffs {
+    /// Same cutoff for all pairs of types.
+    #[must_use] pub fn new(cutoff: f64) -> Self {
+        Self {
+            default: Some(cutoff),
+            pairs: HashMap::new(),
+        }
+    }
+
+    #[must_use] pub fn with_pair(mut self, a: usize, b: usize, cutoff: f64) -> Self {
+        self.pairs.insert(pair_key(a, b), cutoff);
+        self
+    }
+
+    /// Cutoff of the pair, `None` if the pair never bonds.
+    #[must_use] pub fn get(&self, a: usize, b: usize) -> Option<f64> {
+        self.pairs.get(&pair_key(a, b)).copied().or(self.default)
+    }
+
+    /// Largest cutoff of all pairs, the radius to build neighbour lists with.
+    #[must_use] pub fn max_cutoff(&self) -> f64 {
+        self.pairs
+            .values()
+            .copied()
+            .chain(self.default)
+            .fold(0.0, f64::max)
+    }
+
+    #[must_use] pub fn is_within(&self, a: usize, b: usize, distance: f64) -> bool {
+        self.get(a, b).is_some_and(|cutoff| distance <= cutoff)
+    }
+}
+
+impl From<f64> for PairCutoffs {
+    fn from(cutoff: f64) -> Self {
+        Self::new(cutoff)
+    }
+}
+
+impl FromStr for PairCutoffs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cutoffs = Self::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (pair, cutoff) = entry.rsplit_once(':').unwrap_or(("*", entry));
+            let cutoff = cutoff
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("invalid cutoff in {entry}"))?;
+            if cutoff < 0.0 {
+                return Err(format!("negative cutoff in {entry}"));
+            }
+            match pair.trim() {
+                "*" => cutoffs.default = Some(cutoff),
+                pair => {
+                    let (a, b) = pair
+                        .split_once('-')
+                        .and_then(|(a, b)| Some((a.parse().ok()?, b.parse().ok()?)))
+                        .ok_or_else(|| format!("invalid type pair in {entry}"))?;
+                    cutoffs.pairs.insert(pair_key(a, b), cutoff);
+                }
+            }
+        }
+        if cutoffs.default.is_none() && cutoffs.pairs.is_empty() {
+            return Err("no cutoffs given".to_string());
+        }
+        Ok(cutoffs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pair_cutoffs() {
+        let cutoffs = "1-1:2.6, 2-1:2.1".parse::<PairCutoffs>().unwrap();
+        assert_eq!(cutoffs.get(1, 1), Some(2.6));
+        assert_eq!(cutoffs.get(1, 2), Some(2.1));
+        assert_eq!(cutoffs.get(2, 2), None);
+        assert_eq!(cutoffs.max_cutoff(), 2.6);
+        assert!(cutoffs.is_within(2, 1, 2.0));
+        assert!(!cutoffs.is_within(2, 2, 0.5));
+
+        let cutoffs = "3.0,2-2:1.9".parse::<PairCutoffs>().unwrap();
+        assert_eq!(cutoffs, PairCutoffs::new(3.0).with_pair(2, 2, 1.9));
+        assert_eq!(cutoffs.get(1, 2), Some(3.0));
+
+        assert!("1-x:2.0".parse::<PairCutoffs>().is_err());
+        assert!("".parse::<PairCutoffs>().is_err());
+    }
+}
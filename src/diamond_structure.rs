@@ -0,0 +1,227 @@
+use rayon::prelude::*;
+
+use crate::{copy_snapshot_with_keys, DumpSnapshot, Neighbor, NeighborList, Vector3};
+
+/// Structure types of the identify diamond structure analysis, numbered as
+/// in OVITO.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DiamondStructure {
+    Other = 0,
+    CubicDiamond = 1,
+    CubicDiamondFirstNeighbor = 2,
+    CubicDiamondSecondNeighbor = 3,
+    HexagonalDiamond = 4,
+    HexagonalDiamondFirstNeighbor = 5,
+    HexagonalDiamondSecondNeighbor = 6,
+}
+
+impl DiamondStructure {
+    pub const ALL: [Self; 7] = [
+        Self::Other,
+        Self::CubicDiamond,
+        Self::CubicDiamondFirstNeighbor,
+        Self::CubicDiamondSecondNeighbor,
+        Self::HexagonalDiamond,
+        Self::HexagonalDiamondFirstNeighbor,
+        Self::HexagonalDiamondSecondNeighbor,
+    ];
+
+    /// Any of the diamond types including the neighbour ones.
+    #[must_use] pub fn is_diamond(self) -> bool {
+        self != Self::Other
+    }
+
+    const fn first_neighbor(self) -> Self {
+        match self {
+            Self::CubicDiamond => Self::CubicDiamondFirstNeighbor,
+            Self::HexagonalDiamond => Self::HexagonalDiamondFirstNeighbor,
+            _ => Self::Other,
+        }
+    }
+
+    const fn second_neighbor(self) -> Self {
+        match self {
+            Self::CubicDiamond => Self::CubicDiamondSecondNeighbor,
+            Self::HexagonalDiamond => Self::HexagonalDiamondSecondNeighbor,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Four nearest neighbours of every atom sorted by distance, `None` for atoms
+/// with fewer neighbours within the search radius.
+fn get_nearest(neighbors: &NeighborList) -> Vec<Option<[Neighbor; 4]>> {
+    (0..neighbors.len())
+        .into_par_iter()
+        .map(|i| {
+            let mut nearest = neighbors.neighbors(i).to_vec();
+            nearest.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+            nearest.get(..4).map(|n| [n[0], n[1], n[2], n[3]])
+        })
+        .collect()
+}
+
+/// Common neighbour analysis of the twelve second neighbours, they form a
+/// cuboctahedron (421 signatures only) in cubic diamond and an
+/// anticuboctahedron (six 421 and six 422) in hexagonal diamond.
+fn get_cna_structure(second: &[Vector3<f64>]) -> DiamondStructure {
+    let n = second.len();
+    let mean = second.iter().map(|v| v.length()).sum::<f64>() / n as f64;
+    let cutoff_sq = ((1.0 + 2.0_f64.sqrt()) / 2.0 * mean).powi(2);
+    let bonded = |a: usize, b: usize| a != b && second[a].distance_squared(second[b]) < cutoff_sq;
+    let mut count_421 = 0;
+    let mut count_422 = 0;
+    for a in 0..n {
+        let common = (0..n).filter(|&b| bonded(a, b)).collect::<Vec<_>>();
+        if common.len() != 4 {
+            return DiamondStructure::Other;
+        }
+        let bonds = (0..4)
+            .flat_map(|i| (i + 1..4).map(move |j| (i, j)))
+            .filter(|&(i, j)| bonded(common[i], common[j]))
+            .collect::<Vec<_>>();
+        if bonds.len() != 2 {
+            return DiamondStructure::Other;
+        }
+        let (b1, b2) = (bonds[0], bonds[1]);
+        let chained = b1.0 == b2.0 || b1.0 == b2.1 || b1.1 == b2.0 || b1.1 == b2.1;
+        if chained {
+            count_422 += 1;
+        } else {
+            count_421 += 1;
+        }
+    }
+    match (count_421, count_422) {
+        (12, 0) => DiamondStructure::CubicDiamond,
+        (6, 6) => DiamondStructure::HexagonalDiamond,
+        _ => DiamondStructure::Other,
+    }
+}
+
+/// Structure of the atom and indices of its twelve second neighbours.
+fn get_atom_structure(
+    nearest: &[Option<[Neighbor; 4]>],
+    atom_i: usize,
+) -> (DiamondStructure, Vec<usize>) {
+    let Some(first) = &nearest[atom_i] else {
+        return (DiamondStructure::Other, Vec::new());
+    };
+    let mut second = Vec::with_capacity(12);
+    let mut second_indices = Vec::with_capacity(12);
+    for neigh in first {
+        let Some(next) = &nearest[neigh.index] else {
+            return (DiamondStructure::Other, Vec::new());
+        };
+        let tolerance = 1e-3 * neigh.distance;
+        let is_central = |n: &Neighbor| (neigh.delta + n.delta).length() < tolerance;
+        if next.iter().filter(|n| is_central(n)).count() != 1 {
+            return (DiamondStructure::Other, Vec::new());
+        }
+        for n in next.iter().filter(|n| !is_central(n)) {
+            second.push(neigh.delta + n.delta);
+            second_indices.push(n.index);
+        }
+    }
+    (get_cna_structure(&second), second_indices)
+}
+
+/// Identify diamond structure analysis of Maras et al. (2016) as implemented
+/// in OVITO.
+///
+/// The four nearest neighbours of an atom are searched within
+/// `search_radius`, which has to be larger than the bond length.
+#[must_use] pub fn identify_diamond_structure(
+    snapshot: &DumpSnapshot,
+    search_radius: f64,
+) -> Vec<DiamondStructure> {
+    let neighbors = NeighborList::new(snapshot, search_radius);
+    let nearest = get_nearest(&neighbors);
+    let (mut structures, second): (Vec<_>, Vec<_>) = (0..nearest.len())
+        .into_par_iter()
+        .map(|i| get_atom_structure(&nearest, i))
+        .unzip();
+    let crystalline = structures
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.is_diamond())
+        .map(|(i, s)| (i, *s))
+        .collect::<Vec<_>>();
+    for &(i, structure) in &crystalline {
+        for neigh in nearest[i].iter().flatten() {
+            if structures[neigh.index] == DiamondStructure::Other {
+                structures[neigh.index] = structure.first_neighbor();
+            }
+        }
+    }
+    for &(i, structure) in &crystalline {
+        for &j in &second[i] {
+            if structures[j] == DiamondStructure::Other {
+                structures[j] = structure.second_neighbor();
+            }
+        }
+    }
+    structures
+}
+
+/// Copy of the snapshot with the `structure` column holding the
+/// [`DiamondStructure`] numbers.
+#[must_use] pub fn diamond_structure_snapshot(snapshot: &DumpSnapshot, search_radius: f64) -> DumpSnapshot {
+    let structures = identify_diamond_structure(snapshot, search_radius);
+    let mut snapshot = copy_snapshot_with_keys(snapshot, ["structure"].into_iter());
+    for (value, structure) in snapshot
+        .get_property_mut("structure")
+        .iter_mut()
+        .zip(structures)
+    {
+        *value = f64::from(structure as u8);
+    }
+    snapshot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    const BASIS: [[f64; 3]; 8] = [
+        [0.0, 0.0, 0.0],
+        [0.0, 0.5, 0.5],
+        [0.5, 0.0, 0.5],
+        [0.5, 0.5, 0.0],
+        [0.25, 0.25, 0.25],
+        [0.25, 0.75, 0.75],
+        [0.75, 0.25, 0.75],
+        [0.75, 0.75, 0.25],
+    ];
+
+    #[test]
+    fn test_cubic_diamond() {
+        let a = 5.43;
+        let n = 3;
+        let positions = (0..n)
+            .flat_map(|i| (0..n).flat_map(move |j| (0..n).map(move |k| [i, j, k])))
+            .flat_map(|cell| {
+                BASIS.iter().map(move |b| {
+                    std::array::from_fn::<_, 3, _>(|d| (f64::from(cell[d]) + b[d]) * a)
+                })
+            })
+            .collect::<Vec<_>>();
+        let column = |d: usize| positions.iter().map(|p| p[d]).collect::<Vec<_>>();
+        let ids = (1..=positions.len()).map(|i| i as f64).collect::<Vec<_>>();
+        let (x, y, mut z) = (column(0), column(1), column(2));
+        // pull one atom out of the lattice
+        z[0] += 1.0;
+        let snapshot = test_util::snapshot(
+            0,
+            "pp pp pp",
+            (f64::from(n) * a) as f32,
+            &[("id", &ids), ("x", &x), ("y", &y), ("z", &z)],
+        );
+        let structures = identify_diamond_structure(&snapshot, 3.0);
+        assert_eq!(structures[0], DiamondStructure::CubicDiamondSecondNeighbor);
+        assert_eq!(structures[4], DiamondStructure::CubicDiamondFirstNeighbor);
+        assert_eq!(structures[20], DiamondStructure::CubicDiamond);
+        let other = structures.iter().filter(|s| !s.is_diamond()).count();
+        assert_eq!(other, 0);
+    }
+}
@@ -8,7 +8,7 @@ use log::debug;
 
 use crate::dump_file::DumpParsingError;
 use crate::geomutil_util::BoundingBox3;
-use crate::XYZ;
+use crate::{Real, Vector3, XYZ};
 
 pub const HEADER_TIMESTEP: &str = "ITEM: TIMESTEP";
 pub const HEADER_NUM_OF_ATOMS: &str = "ITEM: NUMBER OF ATOMS";
@@ -25,6 +25,36 @@ impl SymBox {
     #[must_use] pub fn volume(&self) -> f32 {
         self.bbox.volume()
     }
+
+    /// Lower corner of the box in the requested precision.
+    #[must_use] pub fn lower<T: Real>(&self) -> Vector3<T> {
+        Vector3::from(self.bbox.lower().coords.map(|c| T::from_f64(c.into())))
+    }
+
+    /// Box edge lengths in the requested precision.
+    #[must_use] pub fn dimensions<T: Real>(&self) -> Vector3<T> {
+        Vector3::from(self.bbox.dimensions().coords.map(|c| T::from_f64(c.into())))
+    }
+
+    /// Whether the x, y and z boundaries are periodic (`pp`).
+    #[must_use] pub fn periodic(&self) -> [bool; 3] {
+        let mut periodic = [false; 3];
+        for (p, b) in periodic.iter_mut().zip(self.boundaries.split_whitespace()) {
+            *p = b.starts_with('p');
+        }
+        periodic
+    }
+
+    /// Largest cutoff within which only the nearest image of every atom is
+    /// in range: half of the shortest periodic edge.
+    #[must_use] pub fn max_cutoff(&self) -> f64 {
+        let dimensions = self.dimensions::<f64>();
+        let periodic = self.periodic();
+        (0..3)
+            .filter(|&d| periodic[d])
+            .map(|d| dimensions[d] / 2.0)
+            .fold(f64::INFINITY, f64::min)
+    }
 }
 
 #[derive(Clone)]
@@ -160,6 +190,16 @@ impl DumpSnapshot {
         &self.atoms[start..end]
     }
 
+    /// Components of a vector column stored as `key[1]`, `key[2]`, ... in
+    /// the dump, empty if there is no `key[1]`.
+    #[must_use] pub fn get_vector_property(&self, key: &str) -> Vec<&[f64]> {
+        (1..)
+            .map(|i| format!("{key}[{i}]"))
+            .take_while(|key| self.keys.contains_key(key))
+            .map(|key| self.get_property(&key))
+            .collect()
+    }
+
     pub fn get_property_mut(&mut self, key: &str) -> &mut [f64] {
         let start = self.keys[key] * self.atoms_count;
         let end = start + self.atoms_count;
@@ -191,6 +231,17 @@ impl DumpSnapshot {
         .map(|(i, (&x, &y, &z))| XYZ::from([x as f32, y as f32, z as f32], i))
         .collect()
     }
+
+    /// Atom positions in the requested precision, indexed like the atoms.
+    #[must_use] pub fn get_positions<T: Real>(&self) -> Vec<Vector3<T>> {
+        izip!(
+            self.get_property("x").iter(),
+            self.get_property("y").iter(),
+            self.get_property("z").iter(),
+        )
+        .map(|(&x, &y, &z)| Vector3::new(T::from_f64(x), T::from_f64(y), T::from_f64(z)))
+        .collect()
+    }
 }
 
 impl fmt::Debug for DumpSnapshot {
@@ -0,0 +1,131 @@
+use rayon::prelude::*;
+
+use crate::{copy_snapshot_with_keys, DumpSnapshot, NeighborList};
+
+/// Smallest bond angle of a twofold coordinated sp atom (deg)
+const SP_MIN_ANGLE: f64 = 150.0;
+/// Smallest sum of the three bond angles of a planar sp2 atom (deg)
+const SP2_MIN_ANGLES_SUM: f64 = 345.0;
+/// Largest mean deviation of the six bond angles of an sp3 atom from the
+/// tetrahedral angle (deg)
+const SP3_MAX_DEVIATION: f64 = 15.0;
+const TETRAHEDRAL_ANGLE: f64 = 109.471_220_634_490_7;
+
+/// Hybridisation of an atom guessed from its coordination and bond angles,
+/// numbered like the `hybridization` column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Hybridization {
+    /// Any other coordination or a distorted environment
+    Other = 0,
+    /// Two bonds in a line
+    Sp = 1,
+    /// Three bonds in a plane
+    Sp2 = 2,
+    /// Four bonds close to a tetrahedron
+    Sp3 = 3,
+}
+
+impl Hybridization {
+    pub const ALL: [Self; 4] = [Self::Other, Self::Sp, Self::Sp2, Self::Sp3];
+
+    #[must_use] pub const fn name(self) -> &'static str {
+        match self {
+            Self::Other => "other",
+            Self::Sp => "sp",
+            Self::Sp2 => "sp2",
+            Self::Sp3 => "sp3",
+        }
+    }
+}
+
+/// Hybridisation of every atom of the neighbour list.
+#[must_use] pub fn get_hybridizations(neighbors: &NeighborList) -> Vec<Hybridization> {
+    (0..neighbors.len())
+        .into_par_iter()
+        .map(|i| {
+            let bonds = neighbors.neighbors(i);
+            let angles = bonds
+                .iter()
+                .enumerate()
+                .flat_map(|(j, a)| {
+                    bonds[j + 1..].iter().map(move |b| {
+                        let cos = a.delta.dot(b.delta) / (a.distance * b.distance);
+                        cos.clamp(-1.0, 1.0).acos().to_degrees()
+                    })
+                })
+                .collect::<Vec<_>>();
+            match bonds.len() {
+                2 if angles[0] >= SP_MIN_ANGLE => Hybridization::Sp,
+                3 if angles.iter().sum::<f64>() >= SP2_MIN_ANGLES_SUM => Hybridization::Sp2,
+                4 if angles
+                    .iter()
+                    .map(|angle| (angle - TETRAHEDRAL_ANGLE).abs())
+                    .sum::<f64>()
+                    / 6.0
+                    <= SP3_MAX_DEVIATION =>
+                {
+                    Hybridization::Sp3
+                }
+                _ => Hybridization::Other,
+            }
+        })
+        .collect()
+}
+
+/// Copy of the snapshot with the `hybridization` column, `hybridizations`
+/// must come from the same snapshot.
+#[must_use] pub fn hybridization_snapshot(snapshot: &DumpSnapshot, hybridizations: &[Hybridization]) -> DumpSnapshot {
+    let mut snapshot = copy_snapshot_with_keys(snapshot, ["hybridization"].into_iter());
+    let hybridization_j = snapshot.get_property_index("hybridization");
+    for (i, &hybridization) in hybridizations.iter().enumerate() {
+        snapshot.set_atom_value(hybridization_j, i, f64::from(hybridization as u8));
+    }
+    snapshot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util, NeighborBackend, PairCutoffs};
+
+    #[test]
+    fn test_hybridization() {
+        let h = 3f64.sqrt() / 2.0;
+        let t = 1.0 / 3f64.sqrt();
+        // linear, trigonal planar and tetrahedral centres with their bonds
+        let atoms = [
+            [1.0, 1.0, 1.0],
+            [2.0, 1.0, 1.0],
+            [0.0, 1.0, 1.0],
+            [1.0, 5.0, 1.0],
+            [2.0, 5.0, 1.0],
+            [0.5, 5.0 + h, 1.0],
+            [0.5, 5.0 - h, 1.0],
+            [5.0, 5.0, 5.0],
+            [5.0 + t, 5.0 + t, 5.0 + t],
+            [5.0 + t, 5.0 - t, 5.0 - t],
+            [5.0 - t, 5.0 + t, 5.0 - t],
+            [5.0 - t, 5.0 - t, 5.0 + t],
+        ];
+        let column = |d: usize| atoms.iter().map(|atom| atom[d]).collect::<Vec<_>>();
+        let snapshot = test_util::snapshot(
+            0,
+            "ss ss ss",
+            10.0,
+            &[
+                ("type", &[1.0; 12]),
+                ("x", &column(0)),
+                ("y", &column(1)),
+                ("z", &column(2)),
+            ],
+        );
+        let cutoffs = PairCutoffs::new(1.2);
+        let neighbors = NeighborList::new_with_cutoffs(&snapshot, &cutoffs, NeighborBackend::KdTree);
+        let snapshot = hybridization_snapshot(&snapshot, &get_hybridizations(&neighbors));
+        let hybridization = snapshot.get_property("hybridization");
+        assert_eq!(hybridization[0], f64::from(Hybridization::Sp as u8));
+        assert_eq!(hybridization[1], f64::from(Hybridization::Other as u8));
+        assert_eq!(hybridization[3], f64::from(Hybridization::Sp2 as u8));
+        assert_eq!(hybridization[7], f64::from(Hybridization::Sp3 as u8));
+    }
+}
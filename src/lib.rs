@@ -1,7 +1,28 @@
+mod binning;
+mod cell_list;
+mod cluster_properties;
+mod cluster_tracking;
 mod clusterizer;
+mod coordination;
+mod cutoffs;
+mod diamond_structure;
 mod dump_file;
 mod dump_snapshot;
+mod hybridization;
 mod math;
+mod neighbor;
+mod rdf;
+mod rings;
+mod sputter;
+mod steinhardt;
+mod stress;
+mod structure_factor;
+#[cfg(test)]
+mod test_util;
+mod trajectory;
+mod vector;
+mod voronoi;
+mod wigner_seitz;
 mod xyz;
 
 use anyhow::Result;
@@ -13,14 +34,37 @@ use std::{
     path::{Path, PathBuf},
 };
 
-pub use clusterizer::{clusterize_snapshot, get_cluster_counts, get_max_cluster_id};
+pub use binning::{Axis, BinAccumulator, BinIndex, BinStats, Binning};
+pub use cell_list::CellList;
+pub use cluster_properties::{
+    get_cluster_properties, get_missing_mass_types, ClusterProperties, MVV_TO_EV,
+};
+pub use cluster_tracking::{ClusterEvent, ClusterEventKind, ClusterTracker};
+pub use clusterizer::{clusterize_snapshot, get_cluster_counts, get_max_cluster_id, UnionFind};
+pub use coordination::{coordination_snapshot, Coordination};
+pub use cutoffs::PairCutoffs;
+pub use diamond_structure::{
+    diamond_structure_snapshot, identify_diamond_structure, DiamondStructure,
+};
 pub use dump_file::DumpFile;
 pub use dump_snapshot::{
     copy_snapshot, copy_snapshot_with_indices, copy_snapshot_with_indices_with_keys,
     copy_snapshot_with_keys, DumpSnapshot, SymBox,
 };
 pub use geomutil_util;
-pub use math::{range, IteratorAvg};
+pub use hybridization::{get_hybridizations, hybridization_snapshot, Hybridization};
+pub use math::{fit_cos_power, range, IteratorAvg, Real};
+pub use neighbor::{KdTreeSearch, Neighbor, NeighborBackend, NeighborList, NeighborSearch};
+pub use rdf::{get_slab_volume, PairHistogram};
+pub use rings::{rings_snapshot, RingKind, Rings};
+pub use sputter::{SputterCriteria, SputterCriterion, SputterDetector};
+pub use steinhardt::{get_bond_order, steinhardt_snapshot, BondOrder};
+pub use stress::{get_stress_tensors, stress_snapshot, StressTensor};
+pub use structure_factor::StructureFactor;
+pub use trajectory::Trajectory;
+pub use vector::Vector3;
+pub use voronoi::{get_voronoi_cells, voronoi_snapshot, VoronoiCell};
+pub use wigner_seitz::WignerSeitz;
 pub use xyz::XYZ;
 
 pub struct RunDir {
@@ -73,43 +117,63 @@ where
     Ok(results)
 }
 
+/// Indices of the initial atoms without any final atom within `cutoff`,
+/// through the periodic boundaries too.
+fn get_vacated_indices<S: NeighborSearch<f64>>(
+    initial_snapshot: &DumpSnapshot,
+    final_snapshot: &DumpSnapshot,
+    cutoff: f64,
+) -> Vec<usize> {
+    let sym_box = &final_snapshot.sym_box;
+    let final_positions = neighbor::wrap_positions(&final_snapshot.get_positions(), sym_box);
+    let (images, _) = neighbor::get_periodic_images(&final_positions, sym_box, cutoff);
+    let search = S::build(images, cutoff);
+    let initial_positions = neighbor::wrap_positions(&initial_snapshot.get_positions(), sym_box);
+    initial_positions
+        .iter()
+        .enumerate()
+        .filter(|(_, atom)| search.within_radius(atom, cutoff).is_empty())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Vacated initial atoms clustered, both searched with `backend`.
 fn crater_candidates_snapshot(
     initial_snapshot: &DumpSnapshot,
     final_snapshot: &DumpSnapshot,
     candidate_cutoff: f64,
-    cluster_cutoff: f64,
+    cluster_cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
 ) -> DumpSnapshot {
-    let initial_coords = initial_snapshot.get_coordinates();
-    let final_coords = final_snapshot.get_coordinates();
-    let kdtree = kd_tree::KdTree::build_by_ordered_float(final_coords);
-    let mut indices = Vec::new();
-    for atom in initial_coords {
-        if kdtree
-            .within_radius(&atom, candidate_cutoff as f32)
-            .is_empty()
-        {
-            indices.push(atom.index);
+    let indices = match backend {
+        NeighborBackend::KdTree => {
+            get_vacated_indices::<KdTreeSearch>(initial_snapshot, final_snapshot, candidate_cutoff)
         }
-    }
+        NeighborBackend::CellList => {
+            get_vacated_indices::<CellList>(initial_snapshot, final_snapshot, candidate_cutoff)
+        }
+    };
     let candidates_snapshot = copy_snapshot_with_indices(initial_snapshot, indices.into_iter());
     debug!(
         "crater candidates atom count: {}",
         candidates_snapshot.atoms_count
     );
-    clusterize_snapshot(&candidates_snapshot, cluster_cutoff)
+    clusterize_snapshot(&candidates_snapshot, cluster_cutoffs, backend)
 }
 
 #[must_use] pub fn crater_snapshot(
     initial_snapshot: &DumpSnapshot,
     final_snapshot: &DumpSnapshot,
     candidate_cutoff: f64,
-    cluster_cutoff: f64,
+    cluster_cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
 ) -> DumpSnapshot {
     let candidates_snapshot = &crater_candidates_snapshot(
         initial_snapshot,
         final_snapshot,
         candidate_cutoff,
-        cluster_cutoff,
+        cluster_cutoffs,
+        backend,
     );
     let max_cluster = get_max_cluster_id(candidates_snapshot);
     let cluster = candidates_snapshot.get_property("cluster");
@@ -120,3 +184,43 @@ fn crater_candidates_snapshot(
         .map(|(i, _)| i);
     copy_snapshot_with_indices(candidates_snapshot, indices)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crater_candidates_periodic() {
+        let initial = test_util::snapshot(
+            0,
+            "pp pp ss",
+            10.0,
+            &[
+                ("id", &[1.0, 2.0]),
+                ("type", &[1.0, 1.0]),
+                ("x", &[0.1, 5.0]),
+                ("y", &[5.0, 5.0]),
+                ("z", &[5.0, 5.0]),
+            ],
+        );
+        // the first atom moved across the x face, the second one is gone
+        let final_snapshot = test_util::snapshot(
+            1,
+            "pp pp ss",
+            10.0,
+            &[
+                ("id", &[1.0]),
+                ("type", &[1.0]),
+                ("x", &[9.9]),
+                ("y", &[5.0]),
+                ("z", &[5.0]),
+            ],
+        );
+        for backend in [NeighborBackend::KdTree, NeighborBackend::CellList] {
+            let cutoffs = PairCutoffs::new(3.0);
+            let candidates =
+                crater_candidates_snapshot(&initial, &final_snapshot, 1.0, &cutoffs, backend);
+            assert_eq!(candidates.get_property("id"), &[2.0]);
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use std::fmt;
 use std::iter;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
 macro_rules! impl_range {
     ($($a:ident)*) => ($(
@@ -50,10 +52,150 @@ macro_rules! impl_avg {
 
 impl_avg! { f32 f64 }
 
+/// Floating point precision the geometry types are generic over.
+pub trait Real:
+    Copy
+    + Default
+    + PartialOrd
+    + fmt::Debug
+    + fmt::Display
+    + Send
+    + Sync
+    + 'static
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + iter::Sum
+{
+    const ZERO: Self;
+    const ONE: Self;
+    const INFINITY: Self;
+    const NEG_INFINITY: Self;
+    const EPSILON: Self;
+    fn from_f64(value: f64) -> Self;
+    fn from_usize(value: usize) -> Self;
+    fn to_f64(self) -> f64;
+    fn to_i64(self) -> i64;
+    fn sqrt(self) -> Self;
+    fn cbrt(self) -> Self;
+    fn floor(self) -> Self;
+    fn ceil(self) -> Self;
+    fn round(self) -> Self;
+    fn abs(self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+    fn rem_euclid(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_real {
+    ($($a:ident)*) => ($(
+        impl Real for $a {
+            const ZERO: $a = 0.0;
+            const ONE: $a = 1.0;
+            const INFINITY: $a = $a::INFINITY;
+            const NEG_INFINITY: $a = $a::NEG_INFINITY;
+            const EPSILON: $a = $a::EPSILON;
+
+            fn from_f64(value: f64) -> Self {
+                value as $a
+            }
+
+            fn from_usize(value: usize) -> Self {
+                value as $a
+            }
+
+            fn to_f64(self) -> f64 {
+                self.into()
+            }
+
+            fn to_i64(self) -> i64 {
+                self as i64
+            }
+
+            fn sqrt(self) -> Self {
+                $a::sqrt(self)
+            }
+
+            fn cbrt(self) -> Self {
+                $a::cbrt(self)
+            }
+
+            fn floor(self) -> Self {
+                $a::floor(self)
+            }
+
+            fn ceil(self) -> Self {
+                $a::ceil(self)
+            }
+
+            fn round(self) -> Self {
+                $a::round(self)
+            }
+
+            fn abs(self) -> Self {
+                $a::abs(self)
+            }
+
+            fn min(self, other: Self) -> Self {
+                $a::min(self, other)
+            }
+
+            fn max(self, other: Self) -> Self {
+                $a::max(self, other)
+            }
+
+            fn rem_euclid(self, rhs: Self) -> Self {
+                $a::rem_euclid(self, rhs)
+            }
+        }
+    )*)
+}
+
+impl_real! { f32 f64 }
+
+/// Fits `a cos^n(theta)` to an angular distribution by weighted least squares
+/// of its logarithm, returns `(a, n)`.
+///
+/// `angles` are polar angles in degrees, points with non-positive values or
+/// weights and angles of 90 degrees and more are skipped. Counts are the
+/// natural weights, as the variance of the logarithm goes with `1 / N`.
+#[must_use] pub fn fit_cos_power(angles: &[f64], values: &[f64], weights: &[f64]) -> Option<(f64, f64)> {
+    let points = angles
+        .iter()
+        .zip(values)
+        .zip(weights)
+        .map(|((angle, value), weight)| (angle.to_radians().cos(), *value, *weight))
+        .filter(|&(cos, value, weight)| cos > 0.0 && value > 0.0 && weight > 0.0)
+        .map(|(cos, value, weight)| (cos.ln(), value.ln(), weight))
+        .collect::<Vec<_>>();
+    if points.len() < 2 {
+        return None;
+    }
+    let weights_sum = points.iter().map(|p| p.2).sum::<f64>();
+    let x_mean = points.iter().map(|p| p.2 * p.0).sum::<f64>() / weights_sum;
+    let y_mean = points.iter().map(|p| p.2 * p.1).sum::<f64>() / weights_sum;
+    let sxx = points.iter().map(|p| p.2 * (p.0 - x_mean).powi(2)).sum::<f64>();
+    let sxy = points
+        .iter()
+        .map(|p| p.2 * (p.0 - x_mean) * (p.1 - y_mean))
+        .sum::<f64>();
+    if sxx == 0.0 {
+        return None;
+    }
+    let n = sxy / sxx;
+    Some((n.mul_add(-x_mean, y_mean).exp(), n))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use assert_float_eq::assert_f32_near;
+    use assert_float_eq::{assert_f32_near, assert_f64_near};
 
     #[test]
     fn test_iterator_avg_f32() {
@@ -94,4 +236,14 @@ mod tests {
         });
         assert_eq!(range::f32(4.0, 5.0, 0).count(), 0);
     }
+
+    #[test]
+    fn test_fit_cos_power() {
+        let angles = [5.0, 25.0, 45.0, 65.0, 85.0, 90.0];
+        let values = angles.map(|a: f64| 3.0 * a.to_radians().cos().powf(1.5));
+        let (a, n) = fit_cos_power(&angles, &values, &[1.0; 6]).unwrap();
+        assert_f64_near!(a, 3.0, 32);
+        assert_f64_near!(n, 1.5, 32);
+        assert!(fit_cos_power(&angles[..1], &values[..1], &[1.0]).is_none());
+    }
 }
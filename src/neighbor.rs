@@ -0,0 +1,327 @@
+use kd_tree::KdPoint;
+use log::debug;
+use rayon::prelude::*;
+use std::str::FromStr;
+
+use crate::{CellList, DumpSnapshot, PairCutoffs, Real, SymBox, Vector3};
+
+/// Spatial index answering fixed radius queries over a set of points.
+pub trait NeighborSearch<T: Real>: Sync + Sized {
+    /// Builds the index, `cutoff` is the largest radius that will be queried.
+    fn build(points: Vec<Vector3<T>>, cutoff: T) -> Self;
+
+    /// Positions in the built `points` of those lying within `radius`.
+    fn within_radius(&self, point: &Vector3<T>, radius: T) -> Vec<usize>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct KdItem {
+    pub(crate) coords: [f64; 3],
+    pub(crate) index: usize,
+}
+
+impl KdPoint for KdItem {
+    type Scalar = f64;
+    type Dim = typenum::U3;
+    fn at(&self, i: usize) -> f64 {
+        self.coords[i]
+    }
+}
+
+/// [`NeighborSearch`] over a kd-tree, points are stored in double precision.
+pub struct KdTreeSearch {
+    kdtree: kd_tree::KdTree<KdItem>,
+}
+
+impl<T: Real> NeighborSearch<T> for KdTreeSearch {
+    fn build(points: Vec<Vector3<T>>, _cutoff: T) -> Self {
+        let items = points
+            .into_iter()
+            .enumerate()
+            .map(|(index, point)| KdItem {
+                coords: point.to_array().map(T::to_f64),
+                index,
+            })
+            .collect();
+        Self {
+            kdtree: kd_tree::KdTree::build_by_ordered_float(items),
+        }
+    }
+
+    fn within_radius(&self, point: &Vector3<T>, radius: T) -> Vec<usize> {
+        let query = KdItem {
+            coords: point.to_array().map(T::to_f64),
+            index: 0,
+        };
+        self.kdtree
+            .within_radius(&query, radius.to_f64())
+            .into_iter()
+            .map(|item| item.index)
+            .collect()
+    }
+}
+
+/// Choice of the [`NeighborSearch`] implementation, parsed from `kd-tree` or
+/// `cell-list`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NeighborBackend {
+    #[default]
+    KdTree,
+    CellList,
+}
+
+impl FromStr for NeighborBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kd-tree" => Ok(Self::KdTree),
+            "cell-list" => Ok(Self::CellList),
+            _ => Err(format!("unknown neighbor backend: {s}")),
+        }
+    }
+}
+
+/// Neighbour of an atom found within the cutoff.
+#[derive(Debug, Clone, Copy)]
+pub struct Neighbor<T = f64> {
+    /// Index of the neighbour in the snapshot
+    pub index: usize,
+    /// Minimum image vector pointing from the atom to the neighbour
+    pub delta: Vector3<T>,
+    pub distance: T,
+}
+
+/// Neighbour lists of all atoms in a snapshot.
+///
+/// Periodic boundaries from [`SymBox::periodic`] are handled with the
+/// minimum image convention, an atom is listed once with its nearest image
+/// even when the cutoff exceeds half the box. Distances are computed in the
+/// precision `T`.
+pub struct NeighborList<T = f64> {
+    cutoff: T,
+    neighbors: Vec<Vec<Neighbor<T>>>,
+}
+
+impl<T: Real> NeighborList<T> {
+    #[must_use] pub fn new(snapshot: &DumpSnapshot, cutoff: T) -> Self {
+        Self::new_with_backend(snapshot, cutoff, NeighborBackend::default())
+    }
+
+    #[must_use] pub fn new_with_backend(
+        snapshot: &DumpSnapshot,
+        cutoff: T,
+        backend: NeighborBackend,
+    ) -> Self {
+        let positions = snapshot.get_positions();
+        let sym_box = &snapshot.sym_box;
+        match backend {
+            NeighborBackend::KdTree => {
+                Self::from_positions_with_search::<KdTreeSearch>(&positions, sym_box, cutoff)
+            }
+            NeighborBackend::CellList => {
+                Self::from_positions_with_search::<CellList<T>>(&positions, sym_box, cutoff)
+            }
+        }
+    }
+
+    /// Neighbour list keeping only the pairs within the cutoff of their types.
+    #[must_use] pub fn new_with_cutoffs(
+        snapshot: &DumpSnapshot,
+        cutoffs: &PairCutoffs,
+        backend: NeighborBackend,
+    ) -> Self {
+        let types = snapshot.get_property("type");
+        let cutoff = T::from_f64(cutoffs.max_cutoff());
+        let mut list = Self::new_with_backend(snapshot, cutoff, backend);
+        list.retain(|i, neigh| {
+            cutoffs.is_within(
+                types[i] as usize,
+                types[neigh.index] as usize,
+                neigh.distance.to_f64(),
+            )
+        });
+        list
+    }
+
+    #[must_use] pub fn from_positions(positions: &[Vector3<T>], sym_box: &SymBox, cutoff: T) -> Self {
+        Self::from_positions_with_search::<KdTreeSearch>(positions, sym_box, cutoff)
+    }
+
+    #[must_use] pub fn from_positions_with_search<S: NeighborSearch<T>>(
+        positions: &[Vector3<T>],
+        sym_box: &SymBox,
+        cutoff: T,
+    ) -> Self {
+        assert!(cutoff >= T::ZERO);
+        let positions = wrap_positions(positions, sym_box);
+        let (images, image_indices) = get_periodic_images(&positions, sym_box, cutoff);
+        debug!(
+            "neighbor list: {} atoms, {} images",
+            positions.len(),
+            images.len()
+        );
+        let search = S::build(images.clone(), cutoff);
+        let neighbors = positions
+            .par_iter()
+            .enumerate()
+            .map(|(i, atom)| {
+                let mut neighbors = search
+                    .within_radius(atom, cutoff)
+                    .into_iter()
+                    .map(|j| (image_indices[j], images[j] - *atom))
+                    .filter(|(index, _)| *index != i)
+                    .map(|(index, delta)| Neighbor {
+                        index,
+                        delta,
+                        distance: delta.length(),
+                    })
+                    .collect::<Vec<_>>();
+                // images of the same atom show up when the cutoff exceeds half the box
+                neighbors.sort_by(|a, b| {
+                    a.index
+                        .cmp(&b.index)
+                        .then(a.distance.partial_cmp(&b.distance).unwrap())
+                });
+                neighbors.dedup_by_key(|neigh| neigh.index);
+                neighbors
+            })
+            .collect();
+        Self { cutoff, neighbors }
+    }
+
+    #[must_use] pub const fn cutoff(&self) -> T {
+        self.cutoff
+    }
+
+    /// Number of atoms in the list.
+    #[must_use] pub fn len(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    #[must_use] pub fn is_empty(&self) -> bool {
+        self.neighbors.is_empty()
+    }
+
+    #[must_use] pub fn neighbors(&self, index: usize) -> &[Neighbor<T>] {
+        &self.neighbors[index]
+    }
+
+    /// Keeps only the neighbours `neigh` of atom `i` for which `f(i, neigh)` holds.
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: Fn(usize, &Neighbor<T>) -> bool + Sync,
+    {
+        self.neighbors
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, neighbors)| neighbors.retain(|neigh| f(i, neigh)));
+    }
+
+    /// Every neighbour pair once, as `(i, neighbour)` with `i < neighbour.index`.
+    pub fn pairs(&self) -> impl Iterator<Item = (usize, &Neighbor<T>)> {
+        self.neighbors.iter().enumerate().flat_map(|(i, neighbors)| {
+            neighbors
+                .iter()
+                .filter(move |neigh| i < neigh.index)
+                .map(move |neigh| (i, neigh))
+        })
+    }
+}
+
+/// Puts atoms back into the box along the periodic axes.
+pub(crate) fn wrap_positions<T: Real>(positions: &[Vector3<T>], sym_box: &SymBox) -> Vec<Vector3<T>> {
+    let lo = sym_box.lower::<T>();
+    let dimensions = sym_box.dimensions::<T>();
+    let periodic = sym_box.periodic();
+    positions
+        .iter()
+        .map(|atom| {
+            let mut wrapped = *atom;
+            for i in (0..3).filter(|&i| periodic[i]) {
+                wrapped[i] = lo[i] + (atom[i] - lo[i]).rem_euclid(dimensions[i]);
+            }
+            wrapped
+        })
+        .collect()
+}
+
+/// Atoms together with their periodic images lying within `cutoff` of the
+/// box faces, and the index of the original atom for each of them.
+pub(crate) fn get_periodic_images<T: Real>(
+    positions: &[Vector3<T>],
+    sym_box: &SymBox,
+    cutoff: T,
+) -> (Vec<Vector3<T>>, Vec<usize>) {
+    let lo = sym_box.lower::<T>();
+    let dimensions = sym_box.dimensions::<T>();
+    let hi = lo + dimensions;
+    let periodic = sym_box.periodic();
+    let periods = (-1..=1)
+        .flat_map(|px| (-1..=1).map(move |py| (px, py)))
+        .flat_map(|(px, py)| (-1..=1).map(move |pz| [px, py, pz]))
+        .filter(|period| period.iter().any(|&p| p != 0))
+        .filter(|period| (0..3).all(|i| period[i] == 0 || periodic[i]))
+        .collect::<Vec<_>>();
+    let mut images = positions.to_vec();
+    let mut indices = (0..positions.len()).collect::<Vec<_>>();
+    for (index, atom) in positions.iter().enumerate() {
+        for period in &periods {
+            if (0..3).all(|i| match period[i] {
+                1 => atom[i] < lo[i] + cutoff,
+                -1 => atom[i] > hi[i] - cutoff,
+                _ => true,
+            }) {
+                let shift = Vector3::from(std::array::from_fn(|i| {
+                    T::from_f64(f64::from(period[i])) * dimensions[i]
+                }));
+                images.push(*atom + shift);
+                indices.push(index);
+            }
+        }
+    }
+    (images, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geomutil_util::BoundingBox3;
+    use assert_float_eq::assert_f64_near;
+
+    fn sym_box(boundaries: &str) -> SymBox {
+        SymBox {
+            boundaries: boundaries.to_string(),
+            bbox: BoundingBox3::new([0.0; 3].into(), [10.0; 3].into()),
+        }
+    }
+
+    #[test]
+    fn test_periodic_neighbors() {
+        let positions = [[0.5, 5.0, 0.5], [9.5, 5.0, 9.5], [5.0, 5.0, 5.0]].map(Vector3::from);
+        let list = NeighborList::from_positions(&positions, &sym_box("pp pp pp"), 1.5);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.neighbors(0).len(), 1);
+        let neigh = list.neighbors(0)[0];
+        assert_eq!(neigh.index, 1);
+        assert_f64_near!(neigh.delta.x, -1.0);
+        assert_f64_near!(neigh.delta.z, -1.0);
+        assert_f64_near!(neigh.distance, 2.0f64.sqrt());
+        assert!(list.neighbors(2).is_empty());
+        assert_eq!(list.pairs().count(), 1);
+
+        let list = NeighborList::from_positions(&positions, &sym_box("pp pp ss"), 1.5);
+        assert!(list.neighbors(0).is_empty());
+        assert_eq!(list.pairs().count(), 0);
+    }
+
+    #[test]
+    fn test_neighbors_beyond_half_box() {
+        // both the direct and the periodic image are within the cutoff
+        let positions = [[2.0, 5.0, 5.0], [7.0, 5.0, 5.0]].map(Vector3::from);
+        let list = NeighborList::from_positions(&positions, &sym_box("pp ss ss"), 6.0);
+        assert_eq!(list.neighbors(0).len(), 1);
+        assert_f64_near!(list.neighbors(0)[0].distance, 5.0);
+        assert_eq!(list.pairs().count(), 1);
+    }
+}
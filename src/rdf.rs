@@ -0,0 +1,216 @@
+use std::{collections::BTreeMap, f64::consts::PI};
+
+use crate::{Axis, DumpSnapshot, NeighborBackend, NeighborList};
+
+/// Partial radial distribution functions `g_ab(r)` accumulated over
+/// snapshots.
+///
+/// Every snapshot is normalised by the number density of its `volume`, so
+/// the volume can be the box, a slab of it or a region.
+pub struct PairHistogram {
+    cutoff: f64,
+    n_bins: usize,
+    /// Ordered pair counts per type pair `(a, b)` with `a <= b`
+    counts: BTreeMap<(usize, usize), Vec<f64>>,
+    /// Sums of `N_a * N_b / V` over snapshots
+    norms: BTreeMap<(usize, usize), f64>,
+    /// Sums of the atom counts and of the densities per type
+    atoms: BTreeMap<usize, (f64, f64)>,
+    snapshots_count: usize,
+    backend: NeighborBackend,
+}
+
+impl PairHistogram {
+    /// # Panics
+    ///
+    /// If there are no bins.
+    #[must_use] pub fn new(cutoff: f64, n_bins: usize) -> Self {
+        assert!(n_bins > 0, "PairHistogram needs at least one bin");
+        Self {
+            cutoff,
+            n_bins,
+            counts: BTreeMap::new(),
+            norms: BTreeMap::new(),
+            atoms: BTreeMap::new(),
+            snapshots_count: 0,
+            backend: NeighborBackend::default(),
+        }
+    }
+
+    #[must_use] pub const fn with_backend(mut self, backend: NeighborBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Adds the pairs of the snapshot, only atoms for which `selected`
+    /// holds are counted as centres and neighbours.
+    ///
+    /// # Panics
+    ///
+    /// If the cutoff exceeds [`SymBox::max_cutoff`](crate::SymBox::max_cutoff),
+    /// as the neighbour list keeps only the nearest image of every pair.
+    pub fn add_snapshot(&mut self, snapshot: &DumpSnapshot, volume: f64, selected: &[bool]) {
+        assert!(
+            self.cutoff <= snapshot.sym_box.max_cutoff(),
+            "PairHistogram cutoff exceeds half of the periodic box"
+        );
+        let types = snapshot
+            .get_property("type")
+            .iter()
+            .map(|&t| t as usize)
+            .collect::<Vec<_>>();
+        let mut type_counts = BTreeMap::<usize, f64>::new();
+        for (t, _) in types.iter().zip(selected).filter(|(_, s)| **s) {
+            *type_counts.entry(*t).or_default() += 1.0;
+        }
+        for (&t, &n) in &type_counts {
+            let atoms = self.atoms.entry(t).or_default();
+            atoms.0 += n;
+            atoms.1 += n / volume;
+        }
+        for (&a, &n_a) in &type_counts {
+            for (&b, &n_b) in type_counts.range(a..) {
+                *self.norms.entry((a, b)).or_default() += n_a * n_b / volume;
+                self.counts
+                    .entry((a, b))
+                    .or_insert_with(|| vec![0.0; self.n_bins]);
+            }
+        }
+        let neighbors = NeighborList::new_with_backend(snapshot, self.cutoff, self.backend);
+        let width = self.cutoff / self.n_bins as f64;
+        for (i, neigh) in neighbors.pairs() {
+            let j = neigh.index;
+            if !selected[i] || !selected[j] {
+                continue;
+            }
+            let bin = ((neigh.distance / width) as usize).min(self.n_bins - 1);
+            let key = (types[i].min(types[j]), types[i].max(types[j]));
+            let counts = self.counts.get_mut(&key).unwrap();
+            // like pairs are counted from both of the atoms
+            counts[bin] += if key.0 == key.1 { 2.0 } else { 1.0 };
+        }
+        self.snapshots_count += 1;
+    }
+
+    #[must_use] pub const fn cutoff(&self) -> f64 {
+        self.cutoff
+    }
+
+    #[must_use] pub const fn snapshots_count(&self) -> usize {
+        self.snapshots_count
+    }
+
+    /// Centres of the distance bins.
+    #[must_use] pub fn get_radii(&self) -> Vec<f64> {
+        let width = self.cutoff / self.n_bins as f64;
+        (0..self.n_bins).map(|i| (i as f64 + 0.5) * width).collect()
+    }
+
+    fn get_shell_volumes(&self) -> Vec<f64> {
+        let width = self.cutoff / self.n_bins as f64;
+        (0..self.n_bins)
+            .map(|i| {
+                let (lo, hi) = (i as f64 * width, (i + 1) as f64 * width);
+                4.0 / 3.0 * PI * (hi.powi(3) - lo.powi(3))
+            })
+            .collect()
+    }
+
+    /// Sorted types present in the added snapshots.
+    #[must_use] pub fn get_types(&self) -> Vec<usize> {
+        self.atoms.keys().copied().collect()
+    }
+
+    /// Average number fraction of every type.
+    #[must_use] pub fn get_concentrations(&self) -> BTreeMap<usize, f64> {
+        let total = self.atoms.values().map(|(n, _)| n).sum::<f64>();
+        self.atoms.iter().map(|(&t, (n, _))| (t, n / total)).collect()
+    }
+
+    /// Average total number density.
+    #[must_use] pub fn get_density(&self) -> f64 {
+        let n = self.snapshots_count.max(1) as f64;
+        self.atoms.values().map(|(_, rho)| rho).sum::<f64>() / n
+    }
+
+    /// Partial `g_ab(r)`, symmetric in `a` and `b`.
+    #[must_use] pub fn get_partial(&self, a: usize, b: usize) -> Vec<f64> {
+        let key = (a.min(b), a.max(b));
+        let (Some(counts), Some(norm)) = (self.counts.get(&key), self.norms.get(&key)) else {
+            return vec![0.0; self.n_bins];
+        };
+        counts
+            .iter()
+            .zip(self.get_shell_volumes())
+            .map(|(count, shell)| count / (norm * shell))
+            .collect()
+    }
+
+    /// Total `g(r)` over all atoms regardless of their types.
+    #[must_use] pub fn get_total(&self) -> Vec<f64> {
+        let concentrations = self.get_concentrations();
+        let mut total = vec![0.0; self.n_bins];
+        for (&a, c_a) in &concentrations {
+            for (&b, c_b) in &concentrations {
+                for (t, g) in total.iter_mut().zip(self.get_partial(a, b)) {
+                    *t += c_a * c_b * g;
+                }
+            }
+        }
+        total
+    }
+}
+
+/// Volume of the slab between `lo` and `hi` along `axis` spanning the box
+/// along the other axes, which leaves out the vacuum above a surface.
+#[must_use] pub fn get_slab_volume(snapshot: &DumpSnapshot, axis: Axis, lo: f64, hi: f64) -> f64 {
+    let dimensions = snapshot.sym_box.dimensions::<f64>();
+    (0..3)
+        .map(|d| if d == axis.index() { hi - lo } else { dimensions[d] })
+        .product()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use assert_float_eq::assert_f64_near;
+
+    #[test]
+    fn test_partial_rdf() {
+        let snapshot = test_util::snapshot(
+            0,
+            "pp pp pp",
+            4.0,
+            &[
+                ("type", &[1.0, 2.0]),
+                ("x", &[0.0, 1.0]),
+                ("y", &[0.0, 0.0]),
+                ("z", &[0.0, 0.0]),
+            ],
+        );
+        let mut histogram = PairHistogram::new(1.5, 3);
+        histogram.add_snapshot(&snapshot, 64.0, &[true, true]);
+        assert_eq!(histogram.get_types(), &[1, 2]);
+        let shell = 4.0 / 3.0 * PI * (1.5f64.powi(3) - 1.0);
+        // the unlike neighbour at 1.0 of each of the atoms
+        assert_f64_near!(histogram.get_partial(2, 1)[2], 1.0 / (shell / 64.0));
+        assert_f64_near!(histogram.get_partial(1, 1)[2], 0.0);
+        assert_f64_near!(histogram.get_density(), 2.0 / 64.0);
+
+        assert_f64_near!(get_slab_volume(&snapshot, Axis::Z, 0.5, 1.0), 8.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "half of the periodic box")]
+    fn test_rdf_cutoff_beyond_half_box() {
+        let snapshot = test_util::snapshot(
+            0,
+            "pp pp ss",
+            4.0,
+            &[("type", &[1.0]), ("x", &[0.0]), ("y", &[0.0]), ("z", &[0.0])],
+        );
+        let mut histogram = PairHistogram::new(2.5, 3);
+        histogram.add_snapshot(&snapshot, 64.0, &[true]);
+    }
+}
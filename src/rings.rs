@@ -0,0 +1,308 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    str::FromStr,
+};
+
+use itertools::Itertools;
+use rayon::prelude::*;
+
+use crate::{copy_snapshot_with_keys, DumpSnapshot, NeighborList, Vector3};
+
+/// Ring definition, parsed from `king` or `primitive`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RingKind {
+    /// Shortest rings through every atom and two of its neighbours
+    King,
+    /// Shortest-path rings, which have no shortcut between any two of their
+    /// atoms and so cannot be split into two smaller rings
+    #[default]
+    Primitive,
+}
+
+impl FromStr for RingKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "king" => Ok(Self::King),
+            "primitive" => Ok(Self::Primitive),
+            _ => Err(format!("unknown ring kind: {s}")),
+        }
+    }
+}
+
+/// Bond graph with the minimum image vectors of the bonds.
+struct Graph {
+    bonds: Vec<Vec<(usize, Vector3<f64>)>>,
+}
+
+impl Graph {
+    fn new(neighbors: &NeighborList) -> Self {
+        let bonds = (0..neighbors.len())
+            .map(|i| {
+                neighbors
+                    .neighbors(i)
+                    .iter()
+                    .filter(|neigh| neigh.index != i)
+                    .unique_by(|neigh| neigh.index)
+                    .map(|neigh| (neigh.index, neigh.delta))
+                    .collect()
+            })
+            .collect();
+        Self { bonds }
+    }
+
+    fn get_delta(&self, a: usize, b: usize) -> Vector3<f64> {
+        self.bonds[a].iter().find(|(j, _)| *j == b).unwrap().1
+    }
+
+    /// Bond distances from `start` up to `max_depth`, never through `avoid`.
+    fn get_distances(&self, start: usize, max_depth: usize, avoid: Option<usize>) -> HashMap<usize, usize> {
+        let mut distances = HashMap::from([(start, 0)]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(atom) = queue.pop_front() {
+            let depth = distances[&atom];
+            if depth == max_depth {
+                continue;
+            }
+            for &(next, _) in &self.bonds[atom] {
+                if Some(next) != avoid && !distances.contains_key(&next) {
+                    distances.insert(next, depth + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+        distances
+    }
+
+    /// Whether the ring closes in space instead of winding around the box.
+    fn is_closed(&self, ring: &[usize]) -> bool {
+        let sum = ring
+            .iter()
+            .circular_tuple_windows()
+            .fold(Vector3::zero(), |sum, (&a, &b)| sum + self.get_delta(a, b));
+        sum.length() < 1e-6
+    }
+
+    /// Whether every pair of ring atoms is as close in the graph as along
+    /// the ring.
+    fn is_shortest_path(&self, ring: &[usize]) -> bool {
+        let n = ring.len();
+        ring.iter().enumerate().all(|(i, &a)| {
+            let distances = self.get_distances(a, n / 2, None);
+            ring.iter().enumerate().skip(i + 2).all(|(j, b)| {
+                let along = (j - i).min(n - j + i);
+                distances.get(b).is_some_and(|&d| d == along)
+            })
+        })
+    }
+
+    /// Rings up to `max_size` whose smallest atom is `start`.
+    fn get_primitive_rings(&self, start: usize, max_size: usize) -> Vec<Vec<usize>> {
+        let distances = self.get_distances(start, max_size / 2, None);
+        let distance = |atom| distances.get(&atom).copied().unwrap_or(max_size / 2 + 1);
+        let mut rings = Vec::new();
+        let mut path = vec![start];
+        let mut stack = vec![self.bonds[start].iter()];
+        while let Some(bonds) = stack.last_mut() {
+            let Some(&(next, _)) = bonds.next() else {
+                stack.pop();
+                path.pop();
+                continue;
+            };
+            let depth = path.len();
+            if next == start && depth >= 3 && path[1] < path[depth - 1] {
+                if self.is_closed(&path) && self.is_shortest_path(&path) {
+                    rings.push(path.clone());
+                }
+                continue;
+            }
+            if next <= start || path.contains(&next) || depth + distance(next) > max_size {
+                continue;
+            }
+            path.push(next);
+            stack.push(self.bonds[next].iter());
+        }
+        rings
+    }
+
+    /// Shortest rings through `center` and every pair of its neighbours.
+    fn get_king_rings(&self, center: usize, max_size: usize) -> Vec<Vec<usize>> {
+        let mut rings = Vec::new();
+        for ((a, _), (b, _)) in self.bonds[center].iter().tuple_combinations() {
+            let distances = self.get_distances(*a, max_size - 2, Some(center));
+            let Some(&length) = distances.get(b) else {
+                continue;
+            };
+            // every shortest path walked back from b
+            let mut paths = vec![vec![*b]];
+            for depth in (0..length).rev() {
+                paths = paths
+                    .into_iter()
+                    .flat_map(|path| {
+                        let last = *path.last().unwrap();
+                        self.bonds[last]
+                            .iter()
+                            .filter(|(prev, _)| distances.get(prev) == Some(&depth))
+                            .map(move |(prev, _)| {
+                                let mut path = path.clone();
+                                path.push(*prev);
+                                path
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect();
+            }
+            for path in paths {
+                let ring = std::iter::once(center).chain(path).collect::<Vec<_>>();
+                if self.is_closed(&ring) {
+                    rings.push(canonical(ring));
+                }
+            }
+        }
+        rings
+    }
+}
+
+/// Rotation starting at the smallest atom, in the direction of its smaller
+/// neighbour.
+fn canonical(mut ring: Vec<usize>) -> Vec<usize> {
+    let min_i = ring.iter().position_min().unwrap();
+    ring.rotate_left(min_i);
+    if ring[1] > ring[ring.len() - 1] {
+        ring[1..].reverse();
+    }
+    ring
+}
+
+/// Rings of the bond graph given by a neighbour list.
+///
+/// Rings winding around periodic boundaries are left out.
+pub struct Rings {
+    atoms_count: usize,
+    rings: Vec<Vec<usize>>,
+}
+
+impl Rings {
+    #[must_use] pub fn new(neighbors: &NeighborList, max_size: usize, kind: RingKind) -> Self {
+        let graph = Graph::new(neighbors);
+        let found = (0..neighbors.len())
+            .into_par_iter()
+            .flat_map_iter(|i| match kind {
+                RingKind::King => graph.get_king_rings(i, max_size),
+                RingKind::Primitive => graph.get_primitive_rings(i, max_size),
+            })
+            .collect::<HashSet<_>>();
+        let mut rings = found.into_iter().collect::<Vec<_>>();
+        rings.sort_unstable_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        Self {
+            atoms_count: neighbors.len(),
+            rings,
+        }
+    }
+
+    /// Rings as atom indices in bond order, sorted by size.
+    #[must_use] pub fn rings(&self) -> &[Vec<usize>] {
+        &self.rings
+    }
+
+    /// Number of rings per ring size.
+    #[must_use] pub fn get_size_counts(&self) -> BTreeMap<usize, usize> {
+        self.rings.iter().map(Vec::len).counts().into_iter().collect()
+    }
+
+    /// Number of rings of every size each atom belongs to.
+    #[must_use] pub fn get_atom_rings(&self) -> Vec<BTreeMap<usize, usize>> {
+        let mut atoms = vec![BTreeMap::new(); self.atoms_count];
+        for ring in &self.rings {
+            for &atom in ring {
+                *atoms[atom].entry(ring.len()).or_default() += 1;
+            }
+        }
+        atoms
+    }
+}
+
+/// Copy of the snapshot with the ring membership columns `rings` and
+/// `rings_<n>` for ring sizes 3 to `max_size`, `rings` must come from the
+/// same snapshot.
+#[must_use] pub fn rings_snapshot(snapshot: &DumpSnapshot, rings: &Rings, max_size: usize) -> DumpSnapshot {
+    let atom_rings = rings.get_atom_rings();
+    let keys = std::iter::once("rings".to_string())
+        .chain((3..=max_size).map(|n| format!("rings_{n}")))
+        .collect::<Vec<_>>();
+    let mut snapshot = copy_snapshot_with_keys(snapshot, keys.iter().map(String::as_str));
+    let total_j = snapshot.get_property_index("rings");
+    let size_j = (3..=max_size)
+        .map(|n| snapshot.get_property_index(&format!("rings_{n}")))
+        .collect::<Vec<_>>();
+    for (i, counts) in atom_rings.iter().enumerate() {
+        snapshot.set_atom_value(total_j, i, counts.values().sum::<usize>() as f64);
+        for (n, &j) in (3..).zip(&size_j) {
+            snapshot.set_atom_value(j, i, counts.get(&n).copied().unwrap_or(0) as f64);
+        }
+    }
+    snapshot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geomutil_util::BoundingBox3;
+    use crate::{test_util, NeighborBackend, PairCutoffs};
+
+    fn get_rings(x: &[f64], y: &[f64], box_size: [f32; 2], kind: RingKind) -> Rings {
+        let mut snapshot = test_util::snapshot(
+            0,
+            "pp pp ss",
+            10.0,
+            &[
+                ("type", &vec![1.0; x.len()]),
+                ("x", x),
+                ("y", y),
+                ("z", &vec![0.0; x.len()]),
+            ],
+        );
+        snapshot.sym_box.bbox = BoundingBox3::new([0.0; 3].into(), [box_size[0], box_size[1], 1.0].into());
+        let cutoffs = PairCutoffs::new(1.7);
+        let neighbors = NeighborList::new_with_cutoffs(&snapshot, &cutoffs, NeighborBackend::KdTree);
+        Rings::new(&neighbors, 8, kind)
+    }
+
+    #[test]
+    fn test_triangle_graph() {
+        let rings = get_rings(&[0.0, 1.6, 0.8], &[0.0, 0.0, 1.3856], [20.0; 2], RingKind::Primitive);
+        assert_eq!(rings.get_size_counts(), BTreeMap::from([(3, 1)]));
+    }
+
+    #[test]
+    fn test_two_triangles_graph() {
+        let x = [5.0, 6.6, 5.8, 5.8];
+        let y = [5.0, 5.0, 6.3856, 3.6144];
+        let rings = get_rings(&x, &y, [20.0; 2], RingKind::Primitive);
+        // the outer 4-ring has the shared bond as a shortcut
+        assert_eq!(rings.get_size_counts(), BTreeMap::from([(3, 2)]));
+        assert_eq!(rings.get_atom_rings()[0], BTreeMap::from([(3, 2)]));
+        let rings = get_rings(&x, &y, [20.0; 2], RingKind::King);
+        assert_eq!(rings.get_size_counts(), BTreeMap::from([(3, 2), (4, 1)]));
+    }
+
+    #[test]
+    fn test_periodic_graphene() {
+        let a = 1.42;
+        let h = 3f64.sqrt() / 2.0 * a;
+        let (mut x, mut y) = (Vec::new(), Vec::new());
+        for (i, j) in (0..4).cartesian_product(0..3) {
+            let (x0, y0) = (3.0 * a * f64::from(i), 2.0 * h * f64::from(j));
+            x.extend([x0, x0 + a, x0 + 1.5 * a, x0 + 2.5 * a]);
+            y.extend([y0, y0, y0 + h, y0 + h]);
+        }
+        let box_size = [(12.0 * a) as f32, (6.0 * h) as f32];
+        for kind in [RingKind::King, RingKind::Primitive] {
+            let rings = get_rings(&x, &y, box_size, kind);
+            // one hexagon per two atoms, the chains around the box are no rings
+            assert_eq!(rings.get_size_counts(), BTreeMap::from([(6, 24)]));
+            assert!(rings.get_atom_rings().iter().all(|atom| atom[&6] == 3));
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::{
+    clusterize_snapshot, copy_snapshot_with_keys, get_cluster_properties,
+    get_missing_mass_types, DumpSnapshot, NeighborBackend, PairCutoffs,
+};
+
+/// Condition on a cluster for its atoms to count as sputtered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SputterCriterion {
+    /// The lowest atom of the cluster is higher above the zero level (A)
+    Height(f64),
+    /// The centre of mass moves outwards (+z) faster (A/ps)
+    Velocity(f64),
+    /// The kinetic energy of the centre of mass is larger (eV), for example
+    /// the surface binding energy
+    Energy(f64),
+    /// The cluster has fewer atoms
+    Size(usize),
+}
+
+impl fmt::Display for SputterCriterion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Height(h) => write!(f, "height>{h}"),
+            Self::Velocity(v) => write!(f, "vz>{v}"),
+            Self::Energy(e) => write!(f, "energy>{e}"),
+            Self::Size(n) => write!(f, "size<{n}"),
+        }
+    }
+}
+
+impl FromStr for SputterCriterion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid criterion {s}");
+        if let Some((name, value)) = s.split_once('>') {
+            let value = value.trim().parse::<f64>().map_err(|_| invalid())?;
+            match name.trim() {
+                "height" => Ok(Self::Height(value)),
+                "vz" => Ok(Self::Velocity(value)),
+                "energy" => Ok(Self::Energy(value)),
+                _ => Err(invalid()),
+            }
+        } else if let Some(("size", value)) = s.split_once('<').map(|(n, v)| (n.trim(), v)) {
+            Ok(Self::Size(value.trim().parse().map_err(|_| invalid())?))
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+/// Sputter criteria that all (`&`) or any (`|`) must hold.
+///
+/// Parsed from a list like `size<1000&height>2.0&vz>0` or
+/// `height>5|energy>4.7`, both separators can't be mixed. The default
+/// `size<1000` takes everything outside the largest clusters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SputterCriteria {
+    criteria: Vec<SputterCriterion>,
+    any: bool,
+}
+
+impl Default for SputterCriteria {
+    fn default() -> Self {
+        Self::all(vec![SputterCriterion::Size(1000)])
+    }
+}
+
+impl SputterCriteria {
+    #[must_use] pub const fn all(criteria: Vec<SputterCriterion>) -> Self {
+        Self { criteria, any: false }
+    }
+
+    #[must_use] pub const fn any(criteria: Vec<SputterCriterion>) -> Self {
+        Self { criteria, any: true }
+    }
+
+    #[must_use] pub fn criteria(&self) -> &[SputterCriterion] {
+        &self.criteria
+    }
+
+    /// Whether there is a height criterion that needs a zero level.
+    #[must_use] pub fn needs_zero_lvl(&self) -> bool {
+        self.criteria
+            .iter()
+            .any(|c| matches!(c, SputterCriterion::Height(_)))
+    }
+
+    fn needs_velocities(&self) -> bool {
+        self.criteria
+            .iter()
+            .any(|c| matches!(c, SputterCriterion::Velocity(_) | SputterCriterion::Energy(_)))
+    }
+}
+
+impl FromStr for SputterCriteria {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('&') && s.contains('|') {
+            return Err(format!("can't mix & and | in {s}"));
+        }
+        let any = s.contains('|');
+        let criteria = s
+            .split(['&', '|'])
+            .map(str::trim)
+            .filter(|c| !c.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        if criteria.is_empty() {
+            return Err("no sputter criteria".to_string());
+        }
+        Ok(Self { criteria, any })
+    }
+}
+
+/// Finds the sputtered clusters of a snapshot, shared by the tools that
+/// split or remove them.
+#[derive(Debug, Clone)]
+pub struct SputterDetector {
+    cutoffs: PairCutoffs,
+    criteria: SputterCriteria,
+    backend: NeighborBackend,
+    zero_lvl: Option<f64>,
+    type_masses: Vec<f64>,
+}
+
+impl SputterDetector {
+    #[must_use] pub const fn new(cutoffs: PairCutoffs, criteria: SputterCriteria) -> Self {
+        Self {
+            cutoffs,
+            criteria,
+            backend: NeighborBackend::KdTree,
+            zero_lvl: None,
+            type_masses: Vec::new(),
+        }
+    }
+
+    /// Neighbour search backend of the clustering.
+    #[must_use] pub const fn with_backend(mut self, backend: NeighborBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Surface level for the height criterion, usually the zero level of the
+    /// initial snapshot.
+    #[must_use] pub const fn with_zero_lvl(mut self, zero_lvl: f64) -> Self {
+        self.zero_lvl = Some(zero_lvl);
+        self
+    }
+
+    /// Masses indexed by atom type for the energy criterion when the
+    /// snapshots have no `mass` column.
+    #[must_use] pub fn with_type_masses(mut self, type_masses: Vec<f64>) -> Self {
+        self.type_masses = type_masses;
+        self
+    }
+
+    /// Atom types of the snapshot without a mass, always none unless there
+    /// is a velocity or energy criterion.
+    #[must_use] pub fn get_missing_mass_types(&self, snapshot: &DumpSnapshot) -> Vec<usize> {
+        if self.criteria.needs_velocities() {
+            get_missing_mass_types(snapshot, &self.type_masses)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Copy of the snapshot with the `cluster` and `sputtered` columns,
+    /// `sputtered` is 1 for every atom of a cluster meeting the criteria.
+    ///
+    /// # Panics
+    ///
+    /// If there is a height criterion without a zero level, or a velocity or
+    /// energy criterion and the snapshot has no velocities or
+    /// [`SputterDetector::get_missing_mass_types`] is not empty.
+    #[must_use] pub fn detect(&self, snapshot: &DumpSnapshot) -> DumpSnapshot {
+        let clustered = clusterize_snapshot(snapshot, &self.cutoffs, self.backend);
+        let clusters = clustered.get_property("cluster");
+        let zs = clustered.get_property("z");
+        let mut sizes = HashMap::<usize, usize>::new();
+        let mut bottoms = HashMap::<usize, f64>::new();
+        for (&cluster, &z) in clusters.iter().zip(zs) {
+            *sizes.entry(cluster as usize).or_default() += 1;
+            let bottom = bottoms.entry(cluster as usize).or_insert(z);
+            *bottom = bottom.min(z);
+        }
+        let properties = if self.criteria.needs_velocities() {
+            get_cluster_properties(&clustered, &self.type_masses)
+                .into_iter()
+                .map(|p| (p.id, p))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let is_sputtered = |cluster: usize| {
+            let mut checks = self.criteria.criteria.iter().map(|criterion| match *criterion {
+                SputterCriterion::Height(h) => {
+                    let zero_lvl = self.zero_lvl.expect("Height criterion needs a zero level");
+                    bottoms[&cluster] - zero_lvl > h
+                }
+                SputterCriterion::Velocity(v) => properties[&cluster].velocity.z > v,
+                SputterCriterion::Energy(e) => properties[&cluster].translational_energy > e,
+                SputterCriterion::Size(n) => sizes[&cluster] < n,
+            });
+            if self.criteria.any {
+                checks.any(|c| c)
+            } else {
+                checks.all(|c| c)
+            }
+        };
+        let sputtered = sizes
+            .keys()
+            .map(|&cluster| (cluster, is_sputtered(cluster)))
+            .collect::<HashMap<_, _>>();
+        let mut snapshot = copy_snapshot_with_keys(&clustered, ["sputtered"].into_iter());
+        let sputtered_j = snapshot.get_property_index("sputtered");
+        for (i, &cluster) in clusters.iter().enumerate() {
+            let value = f64::from(u8::from(sputtered[&(cluster as usize)]));
+            snapshot.set_atom_value(sputtered_j, i, value);
+        }
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn test_sputter_criteria() {
+        let criteria = "size<1000 & height>2.5&vz>0".parse::<SputterCriteria>().unwrap();
+        assert_eq!(
+            criteria,
+            SputterCriteria::all(vec![
+                SputterCriterion::Size(1000),
+                SputterCriterion::Height(2.5),
+                SputterCriterion::Velocity(0.0),
+            ])
+        );
+        let criteria = "energy>4.7|height>5".parse::<SputterCriteria>().unwrap();
+        assert!(criteria.any);
+        assert!("size<10&height>1|vz>0".parse::<SputterCriteria>().is_err());
+        assert!("size>10".parse::<SputterCriteria>().is_err());
+        assert_eq!(SputterCriterion::Energy(4.7).to_string(), "energy>4.7");
+    }
+
+    #[test]
+    fn test_sputter_detector() {
+        // a surface dimer, a slow atom above the surface and a fast one
+        let snapshot = test_util::snapshot(
+            0,
+            "pp pp ss",
+            20.0,
+            &[
+                ("id", &[1.0, 2.0, 3.0, 4.0]),
+                ("type", &[1.0, 1.0, 1.0, 1.0]),
+                ("x", &[1.0, 2.0, 5.0, 10.0]),
+                ("y", &[1.0, 1.0, 5.0, 10.0]),
+                ("z", &[5.0, 5.0, 9.0, 15.0]),
+                ("vx", &[0.0, 0.0, 0.0, 0.0]),
+                ("vy", &[0.0, 0.0, 0.0, 0.0]),
+                ("vz", &[0.0, 0.0, -1.0, 50.0]),
+            ],
+        );
+        let cutoffs = PairCutoffs::new(1.5);
+        let detect = |criteria: &str| {
+            SputterDetector::new(cutoffs.clone(), criteria.parse().unwrap())
+                .with_zero_lvl(5.0)
+                .with_type_masses(vec![0.0, 28.0])
+                .detect(&snapshot)
+                .get_property("sputtered")
+                .to_vec()
+        };
+        assert_eq!(detect("size<2"), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(detect("height>2&vz>0"), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(detect("height>5|vz>0"), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(detect("energy>1.0|size<2"), [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(detect("energy>1.0"), [0.0, 0.0, 0.0, 1.0]);
+        let detector = SputterDetector::new(cutoffs.clone(), "vz>0".parse().unwrap());
+        assert_eq!(detector.get_missing_mass_types(&snapshot), [1]);
+        let detector = SputterDetector::new(cutoffs, SputterCriteria::default());
+        assert!(detector.get_missing_mass_types(&snapshot).is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use std::f64::consts::PI;
+
+use rayon::prelude::*;
+
+use crate::{
+    copy_snapshot_with_keys, DumpSnapshot, NeighborBackend, NeighborList, PairCutoffs, Vector3,
+};
+
+/// Per-atom Steinhardt order parameter `Q_l` and its Lechner-Dellago
+/// average `Q̄_l`, where the complex vectors `q_lm` are averaged over the
+/// atom and its neighbours before taking the norm.
+pub struct BondOrder {
+    pub degree: usize,
+    pub q: Vec<f64>,
+    pub q_avg: Vec<f64>,
+}
+
+/// Associated Legendre polynomials `P_l^m(x)` for `m = 0..=l`, with the
+/// Condon-Shortley phase.
+fn get_legendre(l: usize, x: f64) -> Vec<f64> {
+    let sin = (1.0 - x * x).max(0.0).sqrt();
+    (0..=l)
+        .map(|m| {
+            let mut p_mm = 1.0;
+            for k in 0..m {
+                p_mm *= -((2 * k + 1) as f64) * sin;
+            }
+            if l == m {
+                return p_mm;
+            }
+            let mut p_prev = p_mm;
+            let mut p = x * (2 * m + 1) as f64 * p_mm;
+            for n in m + 2..=l {
+                let p_next = ((2 * n - 1) as f64 * x * p - (n + m - 1) as f64 * p_prev)
+                    / (n - m) as f64;
+                p_prev = p;
+                p = p_next;
+            }
+            p
+        })
+        .collect()
+}
+
+/// Normalisation constants of the spherical harmonics `Y_lm` for `m = 0..=l`.
+fn get_normalization(l: usize) -> Vec<f64> {
+    (0..=l)
+        .map(|m| {
+            let ratio = (l - m + 1..=l + m).map(|k| 1.0 / k as f64).product::<f64>();
+            ((2 * l + 1) as f64 / (4.0 * PI) * ratio).sqrt()
+        })
+        .collect()
+}
+
+/// `q_lm` of every atom for `m = 0..=l` as `(re, im)`, negative `m` follow
+/// from `q_l-m = (-1)^m q_lm*`.
+fn get_qlm(neighbors: &NeighborList, l: usize) -> Vec<Vec<(f64, f64)>> {
+    let normalization = get_normalization(l);
+    (0..neighbors.len())
+        .into_par_iter()
+        .map(|i| {
+            let mut qlm = vec![(0.0, 0.0); l + 1];
+            let neighbors = neighbors.neighbors(i);
+            for neigh in neighbors.iter().filter(|neigh| neigh.distance > 0.0) {
+                let Vector3 { x, y, z } = neigh.delta;
+                let legendre = get_legendre(l, z / neigh.distance);
+                let phi = y.atan2(x);
+                for (m, q) in qlm.iter_mut().enumerate() {
+                    let amplitude = normalization[m] * legendre[m];
+                    let (sin, cos) = (m as f64 * phi).sin_cos();
+                    q.0 += amplitude * cos;
+                    q.1 += amplitude * sin;
+                }
+            }
+            let n = neighbors.len().max(1) as f64;
+            qlm.into_iter().map(|(re, im)| (re / n, im / n)).collect()
+        })
+        .collect()
+}
+
+fn get_norm(qlm: &[(f64, f64)]) -> f64 {
+    let l = qlm.len() - 1;
+    let sum = qlm
+        .iter()
+        .enumerate()
+        .map(|(m, (re, im))| {
+            let weight = if m == 0 { 1.0 } else { 2.0 };
+            weight * (re * re + im * im)
+        })
+        .sum::<f64>();
+    (4.0 * PI / (2 * l + 1) as f64 * sum).sqrt()
+}
+
+#[must_use] pub fn get_bond_order(neighbors: &NeighborList, degree: usize) -> BondOrder {
+    let qlm = get_qlm(neighbors, degree);
+    let q = qlm.par_iter().map(|qlm| get_norm(qlm)).collect();
+    let q_avg = (0..neighbors.len())
+        .into_par_iter()
+        .map(|i| {
+            let atoms = std::iter::once(i).chain(neighbors.neighbors(i).iter().map(|n| n.index));
+            let mut avg = vec![(0.0, 0.0); degree + 1];
+            let mut count = 0;
+            for atom in atoms {
+                count += 1;
+                for (a, q) in avg.iter_mut().zip(&qlm[atom]) {
+                    a.0 += q.0;
+                    a.1 += q.1;
+                }
+            }
+            let avg = avg
+                .into_iter()
+                .map(|(re, im)| (re / f64::from(count), im / f64::from(count)))
+                .collect::<Vec<_>>();
+            get_norm(&avg)
+        })
+        .collect();
+    BondOrder { degree, q, q_avg }
+}
+
+/// Copy of the snapshot with `q<l>` and `q<l>_avg` columns for every degree,
+/// repeated degrees are computed once.
+#[must_use] pub fn steinhardt_snapshot(
+    snapshot: &DumpSnapshot,
+    cutoffs: &PairCutoffs,
+    backend: NeighborBackend,
+    degrees: &[usize],
+) -> DumpSnapshot {
+    let mut degrees = degrees.to_vec();
+    degrees.sort_unstable();
+    degrees.dedup();
+    let neighbors = NeighborList::new_with_cutoffs(snapshot, cutoffs, backend);
+    let keys = degrees
+        .iter()
+        .flat_map(|l| [format!("q{l}"), format!("q{l}_avg")])
+        .collect::<Vec<_>>();
+    let mut snapshot = copy_snapshot_with_keys(snapshot, keys.iter().map(String::as_str));
+    for degree in degrees {
+        let bond_order = get_bond_order(&neighbors, degree);
+        snapshot
+            .get_property_mut(&format!("q{degree}"))
+            .copy_from_slice(&bond_order.q);
+        snapshot
+            .get_property_mut(&format!("q{degree}_avg"))
+            .copy_from_slice(&bond_order.q_avg);
+    }
+    snapshot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use assert_float_eq::assert_f64_near;
+
+    fn lattice(basis: &[[f64; 3]], n: usize) -> DumpSnapshot {
+        let positions = (0..n)
+            .flat_map(|i| (0..n).flat_map(move |j| (0..n).map(move |k| [i, j, k])))
+            .flat_map(|cell| {
+                basis
+                    .iter()
+                    .map(move |b| std::array::from_fn::<_, 3, _>(|d| cell[d] as f64 + b[d]))
+            })
+            .collect::<Vec<_>>();
+        let column = |d: usize| positions.iter().map(|p| p[d]).collect::<Vec<_>>();
+        let ids = (1..=positions.len()).map(|i| i as f64).collect::<Vec<_>>();
+        let types = vec![1.0; positions.len()];
+        test_util::snapshot(
+            0,
+            "pp pp pp",
+            n as f32,
+            &[
+                ("id", &ids),
+                ("type", &types),
+                ("x", &column(0)),
+                ("y", &column(1)),
+                ("z", &column(2)),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_steinhardt_crystals() {
+        let sc = lattice(&[[0.0; 3]], 4);
+        let cutoffs = PairCutoffs::new(1.2);
+        let snapshot = steinhardt_snapshot(&sc, &cutoffs, NeighborBackend::KdTree, &[4, 6]);
+        assert_f64_near!(snapshot.get_property("q4")[0], 0.763_762_615_825_973_3, 100);
+        assert_f64_near!(snapshot.get_property("q6")[7], 0.353_553_390_593_273_8, 100);
+        assert_f64_near!(snapshot.get_property("q6_avg")[7], 0.353_553_390_593_273_8, 100);
+        let snapshot = steinhardt_snapshot(&sc, &cutoffs, NeighborBackend::CellList, &[6, 4, 6]);
+        assert_eq!(snapshot.get_keys().len(), sc.get_keys().len() + 4);
+        assert_f64_near!(snapshot.get_property("q6")[7], 0.353_553_390_593_273_8, 100);
+
+        let fcc = [[0.0, 0.0, 0.0], [0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]];
+        let snapshot = steinhardt_snapshot(
+            &lattice(&fcc, 3),
+            &PairCutoffs::new(0.8),
+            NeighborBackend::KdTree,
+            &[4, 6],
+        );
+        assert_f64_near!(snapshot.get_property("q4")[5], 0.190_940_653_956_492_1, 100);
+        assert_f64_near!(snapshot.get_property("q6_avg")[5], 0.574_524_259_714_910_3, 100);
+    }
+}
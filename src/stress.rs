@@ -0,0 +1,90 @@
+use crate::{copy_snapshot_with_keys, DumpSnapshot};
+
+/// Symmetric stress tensor in the LAMMPS `stress/atom` order
+/// `xx, yy, zz, xy, xz, yz`.
+///
+/// Per-atom values of `compute stress/atom` are stresses times volume, they
+/// become stresses after dividing by an atom or bin volume.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StressTensor(pub [f64; 6]);
+
+impl StressTensor {
+    /// Hydrostatic pressure, the negative mean of the normal stresses.
+    #[must_use] pub fn pressure(&self) -> f64 {
+        let [xx, yy, zz, ..] = self.0;
+        -(xx + yy + zz) / 3.0
+    }
+
+    #[must_use] pub fn von_mises(&self) -> f64 {
+        let [xx, yy, zz, xy, xz, yz] = self.0;
+        let normal = (xx - yy).powi(2) + (yy - zz).powi(2) + (zz - xx).powi(2);
+        let shear = xy * xy + xz * xz + yz * yz;
+        (normal / 2.0 + 3.0 * shear).sqrt()
+    }
+
+    #[must_use] pub fn scale(self, factor: f64) -> Self {
+        Self(self.0.map(|s| s * factor))
+    }
+}
+
+/// Per-atom tensors from the six `key[1]`..`key[6]` columns.
+///
+/// # Panics
+///
+/// If the snapshot has fewer than six components of `key`.
+#[must_use] pub fn get_stress_tensors(snapshot: &DumpSnapshot, key: &str) -> Vec<StressTensor> {
+    let components = snapshot.get_vector_property(key);
+    assert!(
+        components.len() >= 6,
+        "{key} has {} components instead of 6",
+        components.len()
+    );
+    (0..snapshot.atoms_count)
+        .map(|i| StressTensor(std::array::from_fn(|j| components[j][i])))
+        .collect()
+}
+
+/// Copy of the snapshot with the `pressure` and `von_mises` columns computed
+/// from the `key` stress tensors divided by the atom `volumes`.
+#[must_use] pub fn stress_snapshot(snapshot: &DumpSnapshot, key: &str, volumes: &[f64]) -> DumpSnapshot {
+    let tensors = get_stress_tensors(snapshot, key);
+    let mut snapshot = copy_snapshot_with_keys(snapshot, ["pressure", "von_mises"].into_iter());
+    let pressure_j = snapshot.get_property_index("pressure");
+    let von_mises_j = snapshot.get_property_index("von_mises");
+    for (i, (tensor, volume)) in tensors.into_iter().zip(volumes).enumerate() {
+        let stress = tensor.scale(1.0 / volume);
+        snapshot.set_atom_value(pressure_j, i, stress.pressure());
+        snapshot.set_atom_value(von_mises_j, i, stress.von_mises());
+    }
+    snapshot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use assert_float_eq::assert_f64_near;
+
+    #[test]
+    fn test_stress() {
+        let snapshot = test_util::snapshot(
+            0,
+            "pp pp pp",
+            10.0,
+            &[
+                ("id", &[1.0, 2.0]),
+                ("c_stress[1]", &[-6.0, 2.0]),
+                ("c_stress[2]", &[-6.0, 0.0]),
+                ("c_stress[3]", &[-6.0, 0.0]),
+                ("c_stress[4]", &[0.0, 0.0]),
+                ("c_stress[5]", &[0.0, 0.0]),
+                ("c_stress[6]", &[0.0, 1.0]),
+            ],
+        );
+        assert_eq!(snapshot.get_vector_property("c_stress").len(), 6);
+        let snapshot = stress_snapshot(&snapshot, "c_stress", &[2.0, 1.0]);
+        assert_f64_near!(snapshot.get_property("pressure")[0], 3.0);
+        assert_f64_near!(snapshot.get_property("von_mises")[0], 0.0);
+        assert_f64_near!(snapshot.get_property("von_mises")[1], 7.0f64.sqrt());
+    }
+}
@@ -0,0 +1,272 @@
+use std::{collections::BTreeMap, f64::consts::PI};
+
+use itertools::iproduct;
+use rayon::prelude::*;
+
+use crate::{DumpSnapshot, PairHistogram, SymBox, Vector3};
+
+/// Static structure factor `S(q)` with the Ashcroft-Langreth partials
+/// `S_ab(q)` per type pair.
+///
+/// The total is the number-number structure factor
+/// `S(q) = sum_ab sqrt(c_a c_b) S_ab(q)`, equal to the X-ray or neutron one
+/// when all types scatter alike.
+pub struct StructureFactor {
+    q: Vec<f64>,
+    concentrations: BTreeMap<usize, f64>,
+    partials: BTreeMap<(usize, usize), Vec<f64>>,
+}
+
+impl StructureFactor {
+    /// Fourier transform of the partial `g_ab(r)` of the histogram at the
+    /// wave numbers `q`, with the Lorch window against truncation ripples
+    /// if `lorch` is set.
+    #[must_use] pub fn from_rdf(histogram: &PairHistogram, q: &[f64], lorch: bool) -> Self {
+        let radii = histogram.get_radii();
+        let cutoff = histogram.cutoff();
+        let dr = cutoff / radii.len() as f64;
+        let rho = histogram.get_density();
+        let concentrations = histogram.get_concentrations();
+        let window = radii
+            .iter()
+            .map(|&r| {
+                let x = PI * r / cutoff;
+                if lorch { x.sin() / x } else { 1.0 }
+            })
+            .collect::<Vec<_>>();
+        let mut partials = BTreeMap::new();
+        for (&a, c_a) in &concentrations {
+            for (&b, c_b) in concentrations.range(a..) {
+                let g = histogram.get_partial(a, b);
+                let weight = (c_a * c_b).sqrt();
+                let s = q
+                    .iter()
+                    .map(|&q| {
+                        // Faber-Ziman partial turned into the Ashcroft-Langreth one
+                        let integral = radii
+                            .iter()
+                            .zip(&g)
+                            .zip(&window)
+                            .map(|((&r, g), w)| r * r * (g - 1.0) * sinc(q * r) * w)
+                            .sum::<f64>();
+                        let s_fz = 1.0 + 4.0 * PI * rho * integral * dr;
+                        f64::from(u8::from(a == b)) + weight * (s_fz - 1.0)
+                    })
+                    .collect();
+                partials.insert((a, b), s);
+            }
+        }
+        Self {
+            q: q.to_vec(),
+            concentrations,
+            partials,
+        }
+    }
+
+    /// Direct sums `S_ab(q) = Re[rho_a(q) rho_b(-q)] / sqrt(N_a N_b)` over
+    /// the reciprocal vectors of the cell, averaged over the snapshots and
+    /// over spherical shells of width `q_max / n_bins`.
+    ///
+    /// At most `max_vectors` vectors are taken per shell and snapshot, only
+    /// periodic axes contribute to the vectors.
+    #[must_use] pub fn from_snapshots(
+        snapshots: &[&DumpSnapshot],
+        q_max: f64,
+        n_bins: usize,
+        max_vectors: usize,
+    ) -> Self {
+        let mut atoms = BTreeMap::<usize, f64>::new();
+        let mut vectors_count = vec![0usize; n_bins];
+        let mut q_sums = vec![0.0; n_bins];
+        let mut sums = BTreeMap::<(usize, usize), Vec<f64>>::new();
+        for snapshot in snapshots {
+            let positions = snapshot.get_positions::<f64>();
+            let types = snapshot
+                .get_property("type")
+                .iter()
+                .map(|&t| t as usize)
+                .collect::<Vec<_>>();
+            let mut type_counts = BTreeMap::<usize, f64>::new();
+            for &t in &types {
+                *type_counts.entry(t).or_default() += 1.0;
+            }
+            for (&t, &n) in &type_counts {
+                *atoms.entry(t).or_default() += n;
+            }
+            let type_list = type_counts.keys().copied().collect::<Vec<_>>();
+            let type_indices = types
+                .iter()
+                .map(|t| type_list.binary_search(t).unwrap())
+                .collect::<Vec<_>>();
+            let pairs = iproduct!(0..type_list.len(), 0..type_list.len())
+                .filter(|(a, b)| a <= b)
+                .collect::<Vec<_>>();
+            let vectors = get_reciprocal_vectors(&snapshot.sym_box, q_max, n_bins, max_vectors);
+            let values = vectors
+                .par_iter()
+                .map(|(bin, q)| {
+                    let mut rho = vec![(0.0, 0.0); type_list.len()];
+                    for (r, &t) in positions.iter().zip(&type_indices) {
+                        let (sin, cos) = q.dot(*r).sin_cos();
+                        rho[t].0 += cos;
+                        rho[t].1 += sin;
+                    }
+                    let values = pairs
+                        .iter()
+                        .map(|&(a, b)| {
+                            let norm = (type_counts[&type_list[a]] * type_counts[&type_list[b]]).sqrt();
+                            (rho[a].0 * rho[b].0 + rho[a].1 * rho[b].1) / norm
+                        })
+                        .collect::<Vec<_>>();
+                    (*bin, q.length(), values)
+                })
+                .collect::<Vec<_>>();
+            for (bin, q, values) in values {
+                vectors_count[bin] += 1;
+                q_sums[bin] += q;
+                for (&(a, b), value) in pairs.iter().zip(values) {
+                    sums.entry((type_list[a], type_list[b]))
+                        .or_insert_with(|| vec![0.0; n_bins])[bin] += value;
+                }
+            }
+        }
+        let filled = (0..n_bins)
+            .filter(|&bin| vectors_count[bin] > 0)
+            .collect::<Vec<_>>();
+        let q = filled
+            .iter()
+            .map(|&bin| q_sums[bin] / vectors_count[bin] as f64)
+            .collect();
+        let partials = sums
+            .into_iter()
+            .map(|(pair, sums)| {
+                let s = filled
+                    .iter()
+                    .map(|&bin| sums[bin] / vectors_count[bin] as f64)
+                    .collect();
+                (pair, s)
+            })
+            .collect();
+        let total = atoms.values().sum::<f64>();
+        Self {
+            q,
+            concentrations: atoms.into_iter().map(|(t, n)| (t, n / total)).collect(),
+            partials,
+        }
+    }
+
+    /// Wave numbers the factors are given at (1/A).
+    #[must_use] pub fn get_q(&self) -> &[f64] {
+        &self.q
+    }
+
+    /// Sorted atom types.
+    #[must_use] pub fn get_types(&self) -> Vec<usize> {
+        self.concentrations.keys().copied().collect()
+    }
+
+    /// Partial `S_ab(q)`, symmetric in `a` and `b`, `None` if one of the
+    /// types is absent.
+    #[must_use] pub fn get_partial(&self, a: usize, b: usize) -> Option<&[f64]> {
+        self.partials.get(&(a.min(b), a.max(b))).map(Vec::as_slice)
+    }
+
+    #[must_use] pub fn get_total(&self) -> Vec<f64> {
+        let mut total = vec![0.0; self.q.len()];
+        for (&a, c_a) in &self.concentrations {
+            for (&b, c_b) in &self.concentrations {
+                let weight = (c_a * c_b).sqrt();
+                for (t, s) in total.iter_mut().zip(self.get_partial(a, b).unwrap_or_default()) {
+                    *t += weight * s;
+                }
+            }
+        }
+        total
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-8 { 1.0 } else { x.sin() / x }
+}
+
+/// Reciprocal vectors `2 pi (n_x / L_x, n_y / L_y, n_z / L_z)` of the box
+/// along its periodic axes with their shell indices, one of every `q`, `-q`
+/// pair. Shells with more than `max_vectors` vectors are thinned evenly.
+fn get_reciprocal_vectors(
+    sym_box: &SymBox,
+    q_max: f64,
+    n_bins: usize,
+    max_vectors: usize,
+) -> Vec<(usize, Vector3<f64>)> {
+    let dimensions = sym_box.dimensions::<f64>();
+    let periodic = sym_box.periodic();
+    let dq = Vector3::from([0, 1, 2].map(|d| 2.0 * PI / dimensions[d]));
+    let n_max = [0, 1, 2].map(|d| {
+        if periodic[d] { (q_max / dq[d]).floor() as i64 } else { 0 }
+    });
+    let width = q_max / n_bins as f64;
+    let vectors = || {
+        iproduct!(
+            -n_max[0]..=n_max[0],
+            -n_max[1]..=n_max[1],
+            -n_max[2]..=n_max[2]
+        )
+        .filter(|&n| n > (0, 0, 0))
+        .filter_map(move |(i, j, k)| {
+            let q = Vector3::new(i as f64 * dq.x, j as f64 * dq.y, k as f64 * dq.z);
+            let bin = (q.length() / width) as usize;
+            (bin < n_bins).then_some((bin, q))
+        })
+    };
+    let mut counts = vec![0usize; n_bins];
+    for (bin, _) in vectors() {
+        counts[bin] += 1;
+    }
+    let strides = counts
+        .iter()
+        .map(|count| count.div_ceil(max_vectors.max(1)).max(1))
+        .collect::<Vec<_>>();
+    let mut seen = vec![0usize; n_bins];
+    vectors()
+        .filter(|(bin, _)| {
+            seen[*bin] += 1;
+            (seen[*bin] - 1).is_multiple_of(strides[*bin])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use assert_float_eq::assert_f64_near;
+
+    #[test]
+    fn test_direct_structure_factor() {
+        let coordinates = iproduct!(0..2, 0..2, 0..2)
+            .map(|(i, j, k)| [i, j, k].map(f64::from))
+            .collect::<Vec<_>>();
+        let column = |d: usize| coordinates.iter().map(|c| c[d]).collect::<Vec<_>>();
+        let snapshot = test_util::snapshot(
+            0,
+            "pp pp pp",
+            2.0,
+            &[
+                ("type", &[1.0; 8]),
+                ("x", &column(0)),
+                ("y", &column(1)),
+                ("z", &column(2)),
+            ],
+        );
+        let s = StructureFactor::from_snapshots(&[&snapshot], 7.0, 7, 100);
+        assert_eq!(s.get_types(), &[1]);
+        let q = s.get_q();
+        assert_eq!(q.len(), 4);
+        assert_f64_near!(q[0], PI);
+        assert_f64_near!(q[3], 2.0 * PI);
+        // destructive interference between the lattice planes, then a Bragg peak
+        assert!(s.get_partial(1, 1).unwrap()[0].abs() < 1e-9);
+        assert!(s.get_partial(1, 2).is_none());
+        assert_f64_near!(s.get_total()[3], 8.0, 8);
+    }
+}
@@ -0,0 +1,25 @@
+use crate::geomutil_util::BoundingBox3;
+use crate::{DumpSnapshot, SymBox};
+
+/// Snapshot in a `[0, size]` cube built from `(key, values)` columns.
+pub fn snapshot(
+    step: u64,
+    boundaries: &str,
+    size: f32,
+    columns: &[(&str, &[f64])],
+) -> DumpSnapshot {
+    let keys = columns
+        .iter()
+        .enumerate()
+        .map(|(j, (key, _))| ((*key).to_string(), j))
+        .collect();
+    let sym_box = SymBox {
+        boundaries: boundaries.to_string(),
+        bbox: BoundingBox3::new([0.0; 3].into(), [size; 3].into()),
+    };
+    let mut snapshot = DumpSnapshot::new(keys, step, columns[0].1.len(), sym_box);
+    for (key, values) in columns {
+        snapshot.get_property_mut(key).copy_from_slice(values);
+    }
+    snapshot
+}
@@ -0,0 +1,280 @@
+use std::collections::{BTreeSet, HashMap};
+
+use itertools::Itertools;
+use log::debug;
+
+use crate::{DumpSnapshot, SymBox};
+
+/// Per-atom time series of selected snapshot columns, aligned by atom id.
+///
+/// Atoms missing from a snapshot (e.g. deleted sputtered atoms) are marked
+/// as absent and their values are `NaN`.
+#[derive(Debug, Clone)]
+pub struct Trajectory {
+    steps: Vec<u64>,
+    sym_boxes: Vec<SymBox>,
+    ids: Vec<usize>,
+    keys: HashMap<String, usize>,
+    present: Vec<bool>,
+    values: Vec<f64>,
+}
+
+impl Trajectory {
+    /// Builds a trajectory of all atoms found in any of the `snapshots`.
+    pub fn new<'a>(snapshots: impl IntoIterator<Item = &'a DumpSnapshot>, keys: &[&str]) -> Self {
+        let snapshots = snapshots.into_iter().collect::<Vec<_>>();
+        let ids = snapshots
+            .iter()
+            .flat_map(|s| s.get_property("id").iter().map(|&id| id as usize))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        Self::build(snapshots, keys, ids)
+    }
+
+    /// Builds a trajectory of the selected atom `ids` only.
+    pub fn with_ids<'a>(
+        snapshots: impl IntoIterator<Item = &'a DumpSnapshot>,
+        keys: &[&str],
+        ids: &[usize],
+    ) -> Self {
+        let mut ids = ids.to_vec();
+        ids.sort_unstable();
+        ids.dedup();
+        Self::build(snapshots, keys, ids)
+    }
+
+    fn build<'a>(
+        snapshots: impl IntoIterator<Item = &'a DumpSnapshot>,
+        keys: &[&str],
+        ids: Vec<usize>,
+    ) -> Self {
+        let snapshots = snapshots.into_iter().collect::<Vec<_>>();
+        // a repeated key is stored once
+        let keys = keys
+            .iter()
+            .unique()
+            .enumerate()
+            .map(|(j, &key)| (key.to_string(), j))
+            .collect::<HashMap<_, _>>();
+        let mut trajectory = Self {
+            steps: snapshots.iter().map(|s| s.step).collect(),
+            sym_boxes: snapshots.iter().map(|s| s.sym_box.clone()).collect(),
+            present: vec![false; ids.len() * snapshots.len()],
+            values: vec![f64::NAN; keys.len() * ids.len() * snapshots.len()],
+            ids,
+            keys,
+        };
+        let keys = trajectory
+            .keys
+            .iter()
+            .map(|(key, &key_j)| (key.clone(), key_j))
+            .collect::<Vec<_>>();
+        for (step_i, snapshot) in snapshots.into_iter().enumerate() {
+            let indices = snapshot
+                .get_property("id")
+                .iter()
+                .enumerate()
+                .filter_map(|(i, &id)| trajectory.get_atom_index(id as usize).map(|a| (i, a)))
+                .collect::<Vec<_>>();
+            for &(_, atom_i) in &indices {
+                let i = trajectory.get_i(0, atom_i, step_i);
+                trajectory.present[i] = true;
+            }
+            for (key, key_j) in &keys {
+                let property = snapshot.get_property(key);
+                for &(i, atom_i) in &indices {
+                    let value_i = trajectory.get_i(*key_j, atom_i, step_i);
+                    trajectory.values[value_i] = property[i];
+                }
+            }
+        }
+        debug!(
+            "trajectory: {} atoms, {} steps",
+            trajectory.ids.len(),
+            trajectory.steps.len()
+        );
+        trajectory
+    }
+
+    const fn get_i(&self, key_j: usize, atom_i: usize, step_i: usize) -> usize {
+        (key_j * self.ids.len() + atom_i) * self.steps.len() + step_i
+    }
+
+    #[must_use] pub fn steps(&self) -> &[u64] {
+        &self.steps
+    }
+
+    #[must_use] pub fn ids(&self) -> &[usize] {
+        &self.ids
+    }
+
+    #[must_use] pub fn get_keys(&self) -> Vec<&str> {
+        let mut keys: Vec<(&String, &usize)> = self.keys.iter().collect();
+        keys.sort_by(|a, b| a.1.cmp(b.1));
+        keys.into_iter().map(|i| i.0.as_str()).collect()
+    }
+
+    #[must_use] pub fn steps_count(&self) -> usize {
+        self.steps.len()
+    }
+
+    #[must_use] pub fn atoms_count(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Position of the atom `id` in [`Trajectory::ids`].
+    #[must_use] pub fn get_atom_index(&self, id: usize) -> Option<usize> {
+        self.ids.binary_search(&id).ok()
+    }
+
+    /// Position of the timestep `step` in [`Trajectory::steps`].
+    #[must_use] pub fn get_step_index(&self, step: u64) -> Option<usize> {
+        self.steps.iter().position(|&s| s == step)
+    }
+
+    /// Values of the column `key` for the atom at `atom_i` over all steps.
+    #[must_use] pub fn get_series_by_index(&self, key: &str, atom_i: usize) -> &[f64] {
+        let start = self.get_i(self.keys[key], atom_i, 0);
+        &self.values[start..start + self.steps.len()]
+    }
+
+    /// Values of the column `key` for the atom `id` over all steps.
+    #[must_use] pub fn get_series(&self, key: &str, id: usize) -> Option<&[f64]> {
+        self.get_atom_index(id)
+            .map(|atom_i| self.get_series_by_index(key, atom_i))
+    }
+
+    /// Whether the atom at `atom_i` exists at each of the steps.
+    #[must_use] pub fn get_presence_by_index(&self, atom_i: usize) -> &[bool] {
+        let start = self.get_i(0, atom_i, 0);
+        &self.present[start..start + self.steps.len()]
+    }
+
+    #[must_use] pub fn get_presence(&self, id: usize) -> Option<&[bool]> {
+        self.get_atom_index(id)
+            .map(|atom_i| self.get_presence_by_index(atom_i))
+    }
+
+    /// Simulation box at the step at `step_i`.
+    #[must_use] pub fn get_sym_box(&self, step_i: usize) -> &SymBox {
+        &self.sym_boxes[step_i]
+    }
+
+    /// Unwrapped `x`, `y`, `z` of the atom at `atom_i` over all steps.
+    ///
+    /// Image flags `ix`, `iy`, `iz` are used when present in the trajectory,
+    /// otherwise jumps over half the box along periodic axes are undone.
+    #[must_use] pub fn get_unwrapped_coordinates_by_index(&self, atom_i: usize) -> Vec<[f64; 3]> {
+        let coords = ["x", "y", "z"].map(|key| self.get_series_by_index(key, atom_i));
+        let images = ["ix", "iy", "iz"].map(|key| {
+            self.keys
+                .contains_key(key)
+                .then(|| self.get_series_by_index(key, atom_i))
+        });
+        let mut unwrapped = Vec::with_capacity(self.steps.len());
+        let mut shift = [0.0; 3];
+        let mut previous: Option<[f64; 3]> = None;
+        for (step_i, sym_box) in self.sym_boxes.iter().enumerate() {
+            let position = coords.map(|c| c[step_i]);
+            if position.iter().any(|c| c.is_nan()) {
+                unwrapped.push(position);
+                continue;
+            }
+            let dimensions = sym_box.bbox.dimensions();
+            let periodic = sym_box.periodic();
+            for d in 0..3 {
+                let length = f64::from(dimensions[d]);
+                if let Some(image) = images[d] {
+                    shift[d] = image[step_i] * length;
+                } else if let Some(previous) = previous.filter(|_| periodic[d]) {
+                    shift[d] -= ((position[d] - previous[d]) / length).round() * length;
+                }
+            }
+            unwrapped.push(std::array::from_fn(|d| position[d] + shift[d]));
+            previous = Some(position);
+        }
+        unwrapped
+    }
+
+    /// Unwrapped coordinates of the atom `id`, see
+    /// [`Trajectory::get_unwrapped_coordinates_by_index`].
+    #[must_use] pub fn get_unwrapped_coordinates(&self, id: usize) -> Option<Vec<[f64; 3]>> {
+        self.get_atom_index(id)
+            .map(|atom_i| self.get_unwrapped_coordinates_by_index(atom_i))
+    }
+
+    /// Values of the column `key` for all atoms at the step at `step_i`.
+    pub fn get_frame(&self, key: &str, step_i: usize) -> impl Iterator<Item = f64> + '_ {
+        let key_j = self.keys[key];
+        (0..self.ids.len()).map(move |atom_i| self.values[self.get_i(key_j, atom_i, step_i)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use assert_float_eq::assert_f64_near;
+
+    fn snapshot(step: u64, columns: &[(&str, &[f64])]) -> DumpSnapshot {
+        test_util::snapshot(step, "pp pp ss", 1.0, columns)
+    }
+
+    #[test]
+    fn test_trajectory_alignment() {
+        let snapshots = [
+            snapshot(0, &[("id", &[2.0, 1.0, 3.0]), ("z", &[0.2, 0.1, 0.3])]),
+            snapshot(10, &[("id", &[1.0, 2.0]), ("z", &[1.1, 1.2])]),
+            snapshot(20, &[("id", &[4.0, 1.0, 2.0]), ("z", &[2.4, 2.1, 2.2])]),
+        ];
+        let trajectory = Trajectory::new(&snapshots, &["z"]);
+        assert_eq!(trajectory.steps(), &[0, 10, 20]);
+        assert_eq!(trajectory.ids(), &[1, 2, 3, 4]);
+        assert_eq!(trajectory.get_series("z", 1).unwrap(), &[0.1, 1.1, 2.1]);
+        assert_eq!(trajectory.get_presence(3).unwrap(), &[true, false, false]);
+        assert_eq!(trajectory.get_presence(4).unwrap(), &[false, false, true]);
+        assert!(trajectory.get_series("z", 4).unwrap()[0].is_nan());
+        assert!(trajectory.get_series("z", 5).is_none());
+        let frame = trajectory.get_frame("z", 1).collect::<Vec<_>>();
+        assert_eq!(&frame[..2], &[1.1, 1.2]);
+
+        let trajectory = Trajectory::with_ids(&snapshots, &["z"], &[2]);
+        assert_eq!(trajectory.atoms_count(), 1);
+        assert_eq!(trajectory.get_series("z", 2).unwrap(), &[0.2, 1.2, 2.2]);
+    }
+
+    #[test]
+    fn test_trajectory_duplicate_keys() {
+        let snapshots = [
+            snapshot(0, &[("id", &[1.0, 2.0]), ("x", &[0.1, 0.2]), ("z", &[0.3, 0.4])]),
+            snapshot(10, &[("id", &[2.0, 1.0]), ("x", &[1.2, 1.1]), ("z", &[1.4, 1.3])]),
+        ];
+        let trajectory = Trajectory::new(&snapshots, &["z", "x", "z"]);
+        assert_eq!(trajectory.get_keys(), &["z", "x"]);
+        assert_eq!(trajectory.get_series("z", 1).unwrap(), &[0.3, 1.3]);
+        assert_eq!(trajectory.get_series("z", 2).unwrap(), &[0.4, 1.4]);
+        assert_eq!(trajectory.get_series("x", 2).unwrap(), &[0.2, 1.2]);
+    }
+
+    #[test]
+    fn test_trajectory_unwrapping() {
+        let snapshots = [0.9, 0.1, 0.3, 0.8, 0.9].map(|c| {
+            snapshot(0, &[("id", &[1.0]), ("x", &[c]), ("y", &[0.5]), ("z", &[c])])
+        });
+        let trajectory = Trajectory::new(&snapshots, &["x", "y", "z"]);
+        let unwrapped = trajectory.get_unwrapped_coordinates(1).unwrap();
+        let want = [
+            [0.9, 0.5, 0.9],
+            [1.1, 0.5, 0.1],
+            [1.3, 0.5, 0.3],
+            [0.8, 0.5, 0.8],
+            [0.9, 0.5, 0.9],
+        ];
+        for (got, want) in unwrapped.into_iter().zip(want) {
+            for (got, want) in got.into_iter().zip(want) {
+                assert_f64_near!(got, want);
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use rayon::prelude::*;
+
+use crate::{copy_snapshot_with_keys, DumpSnapshot, NeighborList, Vector3};
+
+const EPSILON: f64 = 1e-9;
+
+/// Voronoi cell of an atom, positioned relative to the atom.
+#[derive(Debug, Clone)]
+pub struct VoronoiCell {
+    pub volume: f64,
+    /// Neighbour index and area of every face
+    pub faces: Vec<(usize, f64)>,
+    /// Whether the cell reaches the bounding cube, which happens for atoms on
+    /// free surfaces or with no neighbours close enough
+    pub open: bool,
+}
+
+impl VoronoiCell {
+    /// Number of faces with an area of at least `min_area`.
+    #[must_use] pub fn faces_count(&self, min_area: f64) -> usize {
+        self.faces.iter().filter(|(_, area)| *area >= min_area).count()
+    }
+}
+
+struct Face {
+    neighbor: Option<usize>,
+    normal: Vector3<f64>,
+    vertices: Vec<Vector3<f64>>,
+}
+
+impl Face {
+    fn area(&self) -> f64 {
+        let v0 = self.vertices[0];
+        let sum = self
+            .vertices
+            .windows(2)
+            .skip(1)
+            .map(|w| (w[0] - v0).cross(w[1] - v0))
+            .fold(Vector3::zero(), |a, b| a + b);
+        sum.length() / 2.0
+    }
+}
+
+/// Convex polyhedron around the origin clipped by half-spaces.
+struct Polyhedron {
+    faces: Vec<Face>,
+}
+
+impl Polyhedron {
+    fn cube(half: f64) -> Self {
+        let faces = (0..3)
+            .flat_map(|axis| [-1.0, 1.0].map(move |sign| (axis, sign)))
+            .map(|(axis, sign)| {
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                let vertices = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                    .map(|(a, b)| {
+                        let mut vertex = Vector3::zero();
+                        vertex[axis] = sign * half;
+                        vertex[u] = a * half;
+                        vertex[v] = b * half;
+                        vertex
+                    })
+                    .to_vec();
+                let mut normal = Vector3::zero();
+                normal[axis] = sign;
+                Face {
+                    neighbor: None,
+                    normal,
+                    vertices,
+                }
+            })
+            .collect();
+        Self { faces }
+    }
+
+    fn max_radius(&self) -> f64 {
+        self.faces
+            .iter()
+            .flat_map(|face| &face.vertices)
+            .map(|v| v.length())
+            .fold(0.0, f64::max)
+    }
+
+    /// Keeps the part with `normal . x <= offset`.
+    fn clip(&mut self, normal: Vector3<f64>, offset: f64, neighbor: usize) {
+        let distance = |v: Vector3<f64>| normal.dot(v) - offset;
+        let mut section = Vec::new();
+        for face in &mut self.faces {
+            let n = face.vertices.len();
+            let mut clipped = Vec::with_capacity(n + 1);
+            for k in 0..n {
+                let a = face.vertices[k];
+                let b = face.vertices[(k + 1) % n];
+                let (da, db) = (distance(a), distance(b));
+                if da <= EPSILON {
+                    clipped.push(a);
+                }
+                if (da < -EPSILON && db > EPSILON) || (da > EPSILON && db < -EPSILON) {
+                    let cut = a + (b - a) * (da / (da - db));
+                    clipped.push(cut);
+                    section.push(cut);
+                } else if da.abs() <= EPSILON {
+                    section.push(a);
+                }
+            }
+            face.vertices = clipped;
+        }
+        self.faces.retain(|face| face.vertices.len() >= 3 && face.area() > EPSILON);
+        let vertices = get_section_polygon(section, normal);
+        if vertices.len() >= 3 {
+            self.faces.push(Face {
+                neighbor: Some(neighbor),
+                normal,
+                vertices,
+            });
+        }
+    }
+
+    fn volume(&self) -> f64 {
+        self.faces
+            .iter()
+            .map(|face| {
+                let height = face.normal.dot(face.vertices[0]).abs() / face.normal.length();
+                face.area() * height / 3.0
+            })
+            .sum()
+    }
+}
+
+/// Distinct points of the section ordered around their centroid.
+fn get_section_polygon(points: Vec<Vector3<f64>>, normal: Vector3<f64>) -> Vec<Vector3<f64>> {
+    let mut unique: Vec<Vector3<f64>> = Vec::with_capacity(points.len());
+    for point in points {
+        if unique.iter().all(|u| u.distance_squared(point) > EPSILON) {
+            unique.push(point);
+        }
+    }
+    if unique.len() < 3 {
+        return unique;
+    }
+    let centroid = unique.iter().fold(Vector3::zero(), |a, b| a + *b) / unique.len() as f64;
+    let u = unique[0] - centroid;
+    let v = normal.cross(u);
+    unique.sort_by(|a, b| {
+        let angle = |p: &Vector3<f64>| (*p - centroid).dot(v).atan2((*p - centroid).dot(u));
+        angle(a).total_cmp(&angle(b))
+    });
+    unique
+}
+
+/// Voronoi cells of all atoms in the snapshot.
+///
+/// Cells are built by clipping a cube with the bisector planes of the
+/// neighbours within `cutoff`, periodic boundaries come from the neighbour
+/// list. The cube is small enough for closed cells to be exact, cells still
+/// touching it are reported as open.
+#[must_use] pub fn get_voronoi_cells(snapshot: &DumpSnapshot, cutoff: f64) -> Vec<VoronoiCell> {
+    let neighbors = NeighborList::new(snapshot, cutoff);
+    let half = cutoff / (2.0 * 3.0_f64.sqrt());
+    (0..neighbors.len())
+        .into_par_iter()
+        .map(|i| {
+            let mut neighbors = neighbors.neighbors(i).to_vec();
+            neighbors.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+            let mut polyhedron = Polyhedron::cube(half);
+            let mut max_radius = polyhedron.max_radius();
+            for neigh in &neighbors {
+                if neigh.distance / 2.0 > max_radius {
+                    break;
+                }
+                let offset = neigh.distance * neigh.distance / 2.0;
+                polyhedron.clip(neigh.delta, offset, neigh.index);
+                max_radius = polyhedron.max_radius();
+            }
+            let open = polyhedron.faces.iter().any(|face| face.neighbor.is_none());
+            let faces = polyhedron
+                .faces
+                .iter()
+                .filter_map(|face| face.neighbor.map(|index| (index, face.area())))
+                .collect();
+            VoronoiCell {
+                volume: polyhedron.volume(),
+                faces,
+                open,
+            }
+        })
+        .collect()
+}
+
+/// Copy of the snapshot with the `voronoi_volume`, `voronoi_faces` and
+/// `voronoi_open` columns, faces smaller than `min_face_area` are not counted.
+#[must_use] pub fn voronoi_snapshot(
+    snapshot: &DumpSnapshot,
+    cutoff: f64,
+    min_face_area: f64,
+) -> DumpSnapshot {
+    let cells = get_voronoi_cells(snapshot, cutoff);
+    let mut snapshot = copy_snapshot_with_keys(
+        snapshot,
+        ["voronoi_volume", "voronoi_faces", "voronoi_open"].into_iter(),
+    );
+    for (atom_i, cell) in cells.iter().enumerate() {
+        let values = [
+            cell.volume,
+            cell.faces_count(min_face_area) as f64,
+            f64::from(u8::from(cell.open)),
+        ];
+        for (key, value) in ["voronoi_volume", "voronoi_faces", "voronoi_open"]
+            .into_iter()
+            .zip(values)
+        {
+            let j = snapshot.get_property_index(key);
+            snapshot.set_atom_value(j, atom_i, value);
+        }
+    }
+    snapshot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use assert_float_eq::assert_f64_near;
+
+    fn lattice(basis: &[[f64; 3]], n: usize, boundaries: &str) -> DumpSnapshot {
+        let positions = (0..n)
+            .flat_map(|i| (0..n).flat_map(move |j| (0..n).map(move |k| [i, j, k])))
+            .flat_map(|cell| {
+                basis
+                    .iter()
+                    .map(move |b| std::array::from_fn::<_, 3, _>(|d| cell[d] as f64 + b[d]))
+            })
+            .collect::<Vec<_>>();
+        let column = |d: usize| positions.iter().map(|p| p[d]).collect::<Vec<_>>();
+        let ids = (1..=positions.len()).map(|i| i as f64).collect::<Vec<_>>();
+        test_util::snapshot(
+            0,
+            boundaries,
+            n as f32,
+            &[
+                ("id", &ids),
+                ("x", &column(0)),
+                ("y", &column(1)),
+                ("z", &column(2)),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_voronoi_cells() {
+        let cells = get_voronoi_cells(&lattice(&[[0.0; 3]], 4, "pp pp pp"), 3.0);
+        assert!(cells.iter().all(|cell| !cell.open));
+        assert_f64_near!(cells[0].volume, 1.0, 1000);
+        assert_eq!(cells[0].faces_count(0.0), 6);
+
+        let fcc = [[0.0, 0.0, 0.0], [0.0, 0.5, 0.5], [0.5, 0.0, 0.5], [0.5, 0.5, 0.0]];
+        let snapshot = voronoi_snapshot(&lattice(&fcc, 3, "pp pp pp"), 2.0, 0.0);
+        assert_f64_near!(snapshot.get_property("voronoi_volume")[5], 0.25, 1000);
+        assert_eq!(snapshot.get_property("voronoi_faces")[5], 12.0);
+
+        let snapshot = voronoi_snapshot(&lattice(&[[0.0; 3]], 4, "pp pp ss"), 3.0, 0.0);
+        let open = snapshot.get_property("voronoi_open");
+        let z = snapshot.get_property("z");
+        for (open, z) in open.iter().zip(z) {
+            assert_eq!(*open == 1.0, *z == 0.0 || *z == 3.0);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use rayon::prelude::*;
+
+use crate::neighbor::{get_periodic_images, wrap_positions, KdItem};
+use crate::DumpSnapshot;
+
+/// Wigner-Seitz cell occupancy of the reference sites by the atoms of a
+/// displaced snapshot.
+///
+/// Every displaced atom is assigned to the nearest site of the reference
+/// snapshot, periodic boundaries of the reference box are respected. Empty
+/// sites are vacancies, atoms beyond the first one on a site are
+/// interstitials and singly occupied sites holding an atom of a type other
+/// than the site type are antisites.
+pub struct WignerSeitz {
+    site_indices: Vec<usize>,
+    site_distances: Vec<f64>,
+    occupancy: Vec<usize>,
+    site_types: Vec<usize>,
+    atom_types: Vec<usize>,
+}
+
+fn get_types(snapshot: &DumpSnapshot) -> Vec<usize> {
+    snapshot
+        .get_property("type")
+        .iter()
+        .map(|&t| t as usize)
+        .collect()
+}
+
+impl WignerSeitz {
+    /// # Panics
+    ///
+    /// If the reference snapshot has no atoms while the displaced one has.
+    #[must_use] pub fn new(reference: &DumpSnapshot, displaced: &DumpSnapshot) -> Self {
+        let sym_box = &reference.sym_box;
+        let sites = wrap_positions(&reference.get_positions::<f64>(), sym_box);
+        let spacing = (f64::from(sym_box.volume()) / sites.len().max(1) as f64).cbrt();
+        let (images, image_indices) = get_periodic_images(&sites, sym_box, 3.0 * spacing);
+        let items = images
+            .into_iter()
+            .zip(image_indices)
+            .map(|(image, index)| KdItem {
+                coords: image.to_array(),
+                index,
+            })
+            .collect();
+        let kdtree = kd_tree::KdTree::build_by_ordered_float(items);
+        let atoms = wrap_positions(&displaced.get_positions::<f64>(), sym_box);
+        let (site_indices, site_distances): (Vec<_>, Vec<_>) = atoms
+            .par_iter()
+            .map(|atom| {
+                let query = KdItem {
+                    coords: atom.to_array(),
+                    index: 0,
+                };
+                let nearest = kdtree.nearest(&query).expect("No reference sites");
+                (nearest.item.index, nearest.squared_distance.sqrt())
+            })
+            .unzip();
+        let mut occupancy = vec![0; sites.len()];
+        for &site_i in &site_indices {
+            occupancy[site_i] += 1;
+        }
+        Self {
+            site_indices,
+            site_distances,
+            occupancy,
+            site_types: get_types(reference),
+            atom_types: get_types(displaced),
+        }
+    }
+
+    /// Reference site the displaced atom was assigned to.
+    #[must_use] pub fn get_site_index(&self, atom_i: usize) -> usize {
+        self.site_indices[atom_i]
+    }
+
+    #[must_use] pub fn get_occupancy(&self, site_i: usize) -> usize {
+        self.occupancy[site_i]
+    }
+
+    /// Indices of the empty reference sites.
+    #[must_use] pub fn get_vacancies(&self) -> Vec<usize> {
+        (0..self.occupancy.len())
+            .filter(|&site_i| self.occupancy[site_i] == 0)
+            .collect()
+    }
+
+    /// Indices of the displaced atoms sharing a site with an atom closer to
+    /// the site.
+    #[must_use] pub fn get_interstitials(&self) -> Vec<usize> {
+        let mut closest = vec![None; self.occupancy.len()];
+        for (atom_i, &site_i) in self.site_indices.iter().enumerate() {
+            let distance = self.site_distances[atom_i];
+            match closest[site_i] {
+                Some((_, d)) if d <= distance => {}
+                _ => closest[site_i] = Some((atom_i, distance)),
+            }
+        }
+        (0..self.site_indices.len())
+            .filter(|&atom_i| {
+                let site_i = self.site_indices[atom_i];
+                closest[site_i].is_some_and(|(closest_i, _)| closest_i != atom_i)
+            })
+            .collect()
+    }
+
+    /// Indices of the displaced atoms alone on a site of another type.
+    #[must_use] pub fn get_antisites(&self) -> Vec<usize> {
+        (0..self.site_indices.len())
+            .filter(|&atom_i| {
+                let site_i = self.site_indices[atom_i];
+                self.occupancy[site_i] == 1 && self.site_types[site_i] != self.atom_types[atom_i]
+            })
+            .collect()
+    }
+
+    #[must_use] pub fn get_site_type(&self, site_i: usize) -> usize {
+        self.site_types[site_i]
+    }
+
+    #[must_use] pub fn get_atom_type(&self, atom_i: usize) -> usize {
+        self.atom_types[atom_i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn test_wigner_seitz() {
+        let reference = test_util::snapshot(
+            0,
+            "pp pp ss",
+            10.0,
+            &[
+                ("id", &[1.0, 2.0, 3.0, 4.0]),
+                ("type", &[1.0, 1.0, 2.0, 2.0]),
+                ("x", &[0.5, 3.5, 6.0, 8.0]),
+                ("y", &[5.0, 5.0, 5.0, 5.0]),
+                ("z", &[5.0, 5.0, 5.0, 5.0]),
+            ],
+        );
+        let displaced = test_util::snapshot(
+            0,
+            "pp pp ss",
+            10.0,
+            &[
+                ("id", &[1.0, 2.0, 3.0, 4.0]),
+                ("type", &[1.0, 1.0, 2.0, 1.0]),
+                ("x", &[9.8, 8.1, 3.6, 6.3]),
+                ("y", &[5.0, 5.0, 5.0, 5.0]),
+                ("z", &[5.0, 5.0, 5.0, 5.0]),
+            ],
+        );
+        let ws = WignerSeitz::new(&reference, &displaced);
+        assert_eq!(ws.get_site_index(0), 0);
+        assert_eq!(ws.get_site_index(1), 3);
+        assert_eq!(ws.get_occupancy(0), 1);
+        assert!(ws.get_vacancies().is_empty());
+        assert!(ws.get_interstitials().is_empty());
+        assert_eq!(ws.get_antisites(), &[1, 2, 3]);
+
+        let displaced = test_util::snapshot(
+            0,
+            "pp pp ss",
+            10.0,
+            &[
+                ("id", &[1.0, 2.0, 3.0, 4.0]),
+                ("type", &[1.0, 1.0, 2.0, 2.0]),
+                ("x", &[0.5, 3.5, 5.5, 6.2]),
+                ("y", &[5.0, 5.0, 5.0, 5.0]),
+                ("z", &[5.0, 5.0, 5.0, 5.0]),
+            ],
+        );
+        let ws = WignerSeitz::new(&reference, &displaced);
+        assert_eq!(ws.get_vacancies(), &[3]);
+        assert_eq!(ws.get_occupancy(2), 2);
+        assert_eq!(ws.get_interstitials(), &[2]);
+        assert!(ws.get_antisites().is_empty());
+    }
+}
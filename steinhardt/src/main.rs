@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use itertools::Itertools;
+use lammps_util_rust::{
+    DumpFile, DumpSnapshot, IteratorAvg, NeighborBackend, PairCutoffs, steinhardt_snapshot,
+};
+use log::info;
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Steinhardt bond orientational order parameters and their depth profiles
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    dump_file: PathBuf,
+
+    /// Cutoffs (A) like `1-1:2.6,1-2:2.1,2-2:1.9`, a bare number applies to
+    /// the pairs not listed
+    #[arg(short, long, default_value = "3.0")]
+    cutoffs: PairCutoffs,
+
+    /// Neighbour search backend: kd-tree or cell-list
+    #[arg(short, long, default_value = "kd-tree")]
+    backend: NeighborBackend,
+
+    /// Degrees l of the order parameters
+    #[arg(short, long, value_delimiter = ',', default_value = "4,6")]
+    degrees: Vec<usize>,
+
+    #[arg(short, long)]
+    timestep: Option<u64>,
+
+    /// Width of the depth layers (A)
+    #[arg(short = 'w', long, default_value_t = 5.0)]
+    layer_width: f64,
+
+    /// Surface level, the highest atom by default
+    #[arg(short, long)]
+    zero_lvl: Option<f64>,
+
+    /// Only atoms within this distance of the crater axis (A)
+    #[arg(short, long)]
+    radius: Option<f64>,
+
+    /// X of the crater axis
+    #[arg(long, default_value_t = 0.0)]
+    center_x: f64,
+
+    /// Y of the crater axis
+    #[arg(long, default_value_t = 0.0)]
+    center_y: f64,
+
+    /// Dump file to write with the order parameter columns added
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn get_layers(snapshot: &DumpSnapshot, cli: &Cli) -> BTreeMap<i64, Vec<usize>> {
+    let zero_lvl = cli.zero_lvl.unwrap_or_else(|| snapshot.get_zero_lvl());
+    let x = snapshot.get_property("x");
+    let y = snapshot.get_property("y");
+    let z = snapshot.get_property("z");
+    let mut layers = BTreeMap::<_, Vec<_>>::new();
+    for i in 0..snapshot.atoms_count {
+        let r = (x[i] - cli.center_x).hypot(y[i] - cli.center_y);
+        if cli.radius.is_some_and(|radius| r > radius) {
+            continue;
+        }
+        let layer = ((zero_lvl - z[i]) / cli.layer_width).floor() as i64;
+        layers.entry(layer).or_default().push(i);
+    }
+    layers
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let mut cli = Cli::parse();
+    cli.degrees.sort_unstable();
+    cli.degrees.dedup();
+    let timesteps = cli.timestep.map(|t| vec![t]).unwrap_or_default();
+    let dump = DumpFile::read(&cli.dump_file, &timesteps)?;
+    let snapshot = *dump.get_snapshots().first().context("No snapshots")?;
+    info!("step {}: {} atoms", snapshot.step, snapshot.atoms_count);
+    let snapshot = steinhardt_snapshot(snapshot, &cli.cutoffs, cli.backend, &cli.degrees);
+    let keys = cli
+        .degrees
+        .iter()
+        .flat_map(|l| [format!("q{l}"), format!("q{l}_avg")])
+        .collect::<Vec<_>>();
+    let table = get_layers(&snapshot, &cli)
+        .into_iter()
+        .map(|(layer, indices)| {
+            let depth = (layer as f64 + 0.5) * cli.layer_width;
+            let stats = keys.iter().flat_map(|key| {
+                let values = snapshot.get_property(key);
+                let (avg, std) = indices
+                    .iter()
+                    .map(|&i| values[i])
+                    .avg_with_std()
+                    .unwrap_or_default();
+                [avg, std]
+            });
+            [format!("{depth:10.4}"), indices.len().to_string()]
+                .into_iter()
+                .chain(stats.map(|x| format!("{x:10.4}")))
+                .join("\t")
+        })
+        .join("\n");
+    let header = keys.iter().map(|key| format!("{key} σ({key})")).join(" ");
+    println!("# depth count {header}\n{table}");
+    if let Some(output) = &cli.output {
+        DumpFile::new(vec![snapshot]).save(output)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use anyhow::{Context, Result, bail};
+use clap::{Args, Parser, Subcommand};
+use itertools::Itertools;
+use lammps_util_rust::{
+    Axis, BinAccumulator, Binning, DumpFile, StressTensor, get_voronoi_cells, stress_snapshot,
+};
+use log::info;
+use std::path::PathBuf;
+
+/// Pressure and von Mises stress from the per-atom stress tensors
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    dump_file: PathBuf,
+
+    /// Per-atom stress column, read from `key[1]`..`key[6]`
+    #[arg(short, long, default_value = "c_stress")]
+    key: String,
+
+    /// Timesteps to average over, all by default
+    #[arg(short, long, value_delimiter = ',')]
+    timesteps: Vec<u64>,
+
+    /// Convert the stresses from bar (metal units) to GPa
+    #[arg(long)]
+    gpa: bool,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Profile over the depth below the surface
+    Depth(DepthCMD),
+
+    /// Map over the distance from the impact axis and the depth
+    Map(MapCMD),
+
+    /// Per-atom values with Voronoi volumes written to a dump file
+    Atoms(AtomsCMD),
+}
+
+#[derive(Args)]
+struct DepthCMD {
+    /// Layer width (A)
+    #[arg(short, long, default_value_t = 2.0)]
+    width: f64,
+
+    /// Surface level, the highest atom of the first snapshot by default
+    #[arg(short, long)]
+    zero_lvl: Option<f64>,
+}
+
+#[derive(Args)]
+struct MapCMD {
+    /// Radial bin width (A)
+    #[arg(short, long, default_value_t = 2.0)]
+    radial_width: f64,
+
+    /// Bin width along z (A)
+    #[arg(short, long, default_value_t = 2.0)]
+    axial_width: f64,
+
+    /// Impact point in the xy plane
+    #[arg(short, long, value_delimiter = ',', default_value = "0,0")]
+    center: Vec<f64>,
+
+    /// Surface level, the highest atom of the first snapshot by default
+    #[arg(short, long)]
+    zero_lvl: Option<f64>,
+}
+
+#[derive(Args)]
+struct AtomsCMD {
+    /// Resulting dump file
+    output: PathBuf,
+
+    /// Voronoi neighbour cutoff (A). Atoms whose cell stays open within it,
+    /// such as those on free surfaces, get NaN stresses
+    #[arg(short, long, default_value_t = 10.0)]
+    cutoff: f64,
+}
+
+const COMPONENTS: [&str; 6] = ["xx", "yy", "zz", "xy", "xz", "yz"];
+
+fn print_bins(dump: &DumpFile, binning: Binning, zero_lvl: Option<f64>, cli: &Cli) -> Result<()> {
+    let snapshots = dump.get_snapshots();
+    let first = snapshots.first().context("No snapshots")?;
+    let zero_lvl = zero_lvl.unwrap_or_else(|| first.get_zero_lvl());
+    let keys = (1..=6)
+        .map(|i| format!("{}[{i}]", cli.key))
+        .collect::<Vec<_>>();
+    let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+    let mut accumulator = BinAccumulator::new(binning, &keys);
+    for snapshot in &snapshots {
+        accumulator.add_snapshot(snapshot);
+    }
+    info!("{} snapshots binned", accumulator.snapshots_count());
+    let unit = if cli.gpa { 1e-4 } else { 1.0 };
+    let names = accumulator.binning().get_coordinate_names();
+    let table = accumulator
+        .bins()
+        .map(|(bin, stats)| {
+            let volume = accumulator.get_volume(bin);
+            let stress = StressTensor(std::array::from_fn(|j| accumulator.get_sum(stats, j)))
+                .scale(unit / volume);
+            let coordinates = accumulator
+                .binning()
+                .get_center(bin)
+                .into_iter()
+                .zip(&names)
+                .map(|(x, name)| if *name == "z" { zero_lvl - x } else { x });
+            coordinates
+                .chain([
+                    accumulator.get_count(stats),
+                    stress.pressure(),
+                    stress.von_mises(),
+                ])
+                .chain(stress.0)
+                .map(|x| format!("{x:10.4}"))
+                .join("\t")
+        })
+        .join("\n");
+    let header = names
+        .iter()
+        .map(|name| if *name == "z" { "depth" } else { name })
+        .chain(["count", "pressure", "von_mises"])
+        .chain(COMPONENTS)
+        .join(" ");
+    println!("# {header}\n{table}");
+    Ok(())
+}
+
+fn write_atoms(dump: &DumpFile, args: &AtomsCMD, cli: &Cli) -> Result<()> {
+    let unit = if cli.gpa { 1e-4 } else { 1.0 };
+    let snapshots = dump
+        .get_snapshots()
+        .into_iter()
+        .map(|snapshot| {
+            let volumes = get_voronoi_cells(snapshot, args.cutoff)
+                .into_iter()
+                .map(|cell| {
+                    if cell.open {
+                        f64::NAN
+                    } else {
+                        cell.volume / unit
+                    }
+                })
+                .collect::<Vec<_>>();
+            stress_snapshot(snapshot, &cli.key, &volumes)
+        })
+        .collect();
+    DumpFile::new(snapshots).save(&args.output)?;
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    let dump = DumpFile::read(&cli.dump_file, &cli.timesteps)?;
+    let expected = (1..=6)
+        .map(|i| format!("{}[{i}]", cli.key))
+        .collect::<Vec<_>>();
+    for snapshot in dump.get_snapshots() {
+        let keys = snapshot.get_keys();
+        if !expected.iter().all(|key| keys.contains(&key.as_str())) {
+            bail!(
+                "Step {} lacks the stress columns, expected {}",
+                snapshot.step,
+                expected.join(" ")
+            );
+        }
+    }
+    match &cli.command {
+        Commands::Depth(args) => {
+            let binning = Binning::Cartesian(vec![(Axis::Z, args.width)]);
+            print_bins(&dump, binning, args.zero_lvl, &cli)
+        }
+        Commands::Map(args) => {
+            let Ok(center) = <[f64; 2]>::try_from(args.center.as_slice()) else {
+                bail!("Expected two coordinates of the impact point");
+            };
+            let binning = Binning::Cylindrical {
+                axis: Axis::Z,
+                center,
+                radial_width: args.radial_width,
+                axial_width: Some(args.axial_width),
+            };
+            print_bins(&dump, binning, args.zero_lvl, &cli)
+        }
+        Commands::Atoms(args) => write_atoms(&dump, args, &cli),
+    }
+}
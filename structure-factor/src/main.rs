@@ -0,0 +1,146 @@
+use anyhow::{Result, bail};
+use clap::{Parser, ValueEnum};
+use itertools::Itertools;
+use lammps_util_rust::{Axis, DumpFile, PairHistogram, StructureFactor, get_slab_volume, range};
+use log::info;
+use std::path::PathBuf;
+
+/// Static structure factor S(q) with the partials per type pair
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    dump_file: PathBuf,
+
+    /// Timesteps to average over, all by default
+    #[arg(short, long, value_delimiter = ',')]
+    timesteps: Vec<u64>,
+
+    /// How S(q) is computed
+    #[arg(short, long, default_value = "rdf")]
+    method: Method,
+
+    /// Largest wave number (1/A)
+    #[arg(short, long, default_value_t = 12.0)]
+    q_max: f64,
+
+    /// Number of wave numbers, or of q shells for the direct method
+    #[arg(short, long, default_value_t = 240)]
+    n_q: usize,
+
+    /// RDF cutoff (A)
+    #[arg(short, long, default_value_t = 10.0)]
+    cutoff: f64,
+
+    /// Number of RDF bins
+    #[arg(short = 'b', long, default_value_t = 500)]
+    n_bins: usize,
+
+    /// Only atoms between `zlo,zhi` for the RDF, normalised by the volume of
+    /// this slab of the box, which leaves out the vacuum above a surface
+    #[arg(short, long, value_delimiter = ',')]
+    slab: Option<Vec<f64>>,
+
+    /// Damp the RDF with the Lorch window before the transform
+    #[arg(short, long)]
+    lorch: bool,
+
+    /// Most reciprocal vectors per q shell and snapshot for the direct method
+    #[arg(short = 'v', long, default_value_t = 100)]
+    max_vectors: usize,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Method {
+    /// Fourier transform of the partial RDFs
+    Rdf,
+
+    /// Sums over the reciprocal vectors of the periodic cell
+    Direct,
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    if cli.n_bins == 0 {
+        bail!("The number of RDF bins must be positive");
+    }
+    let slab = match &cli.slab {
+        Some(slab) => match <[f64; 2]>::try_from(slab.as_slice()) {
+            Ok(slab) => Some(slab),
+            Err(_) => bail!("Expected zlo,zhi for the slab"),
+        },
+        None => None,
+    };
+    let dump = DumpFile::read(&cli.dump_file, &cli.timesteps)?;
+    let snapshots = dump.get_snapshots();
+    if snapshots.is_empty() {
+        bail!("No snapshots");
+    }
+    let s = match cli.method {
+        Method::Rdf => {
+            let mut histogram = PairHistogram::new(cli.cutoff, cli.n_bins);
+            for snapshot in &snapshots {
+                let max_cutoff = snapshot.sym_box.max_cutoff();
+                if cli.cutoff > max_cutoff {
+                    bail!(
+                        "Step {}: the cutoff exceeds half of the periodic box, {max_cutoff}",
+                        snapshot.step
+                    );
+                }
+                let (volume, selected) = match slab {
+                    Some([lo, hi]) => (
+                        get_slab_volume(snapshot, Axis::Z, lo, hi),
+                        snapshot
+                            .get_property("z")
+                            .iter()
+                            .map(|&z| lo <= z && z < hi)
+                            .collect(),
+                    ),
+                    None => (
+                        f64::from(snapshot.sym_box.volume()),
+                        vec![true; snapshot.atoms_count],
+                    ),
+                };
+                histogram.add_snapshot(snapshot, volume, &selected);
+            }
+            let dq = cli.q_max / cli.n_q as f64;
+            let q = range::f64(dq, cli.q_max, cli.n_q).collect::<Vec<_>>();
+            StructureFactor::from_rdf(&histogram, &q, cli.lorch)
+        }
+        Method::Direct => {
+            StructureFactor::from_snapshots(&snapshots, cli.q_max, cli.n_q, cli.max_vectors)
+        }
+    };
+    info!("{} snapshots used", snapshots.len());
+    let types = s.get_types();
+    let pairs = types
+        .iter()
+        .tuple_combinations()
+        .chain(types.iter().map(|t| (t, t)))
+        .sorted()
+        .collect::<Vec<_>>();
+    let total = s.get_total();
+    let table = s
+        .get_q()
+        .iter()
+        .enumerate()
+        .map(|(i, q)| {
+            [*q, total[i]]
+                .into_iter()
+                .chain(
+                    pairs
+                        .iter()
+                        .filter_map(|&(a, b)| s.get_partial(*a, *b))
+                        .map(|partial| partial[i]),
+                )
+                .map(|x| format!("{x:10.4}"))
+                .join("\t")
+        })
+        .join("\n");
+    let header = ["q".to_string(), "S".to_string()]
+        .into_iter()
+        .chain(pairs.iter().map(|(a, b)| format!("S_{a}-{b}")))
+        .join(" ");
+    println!("# {header}\n{table}");
+    Ok(())
+}
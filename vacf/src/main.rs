@@ -0,0 +1,213 @@
+use anyhow::{Result, bail};
+use clap::Parser;
+use itertools::Itertools;
+use lammps_util_rust::{DumpFile, Trajectory};
+use log::info;
+use rayon::prelude::*;
+use std::{collections::BTreeMap, f64::consts::PI, iter, path::PathBuf};
+
+/// Velocity autocorrelation function and vibrational density of states
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    dump_file: PathBuf,
+
+    /// Time per simulation step (ps)
+    #[arg(short, long, default_value_t = 0.001)]
+    dt: f64,
+
+    /// Number of frames between time origins
+    #[arg(short, long, default_value_t = 1)]
+    origin_stride: usize,
+
+    /// Maximum correlation lag in frames
+    #[arg(short, long)]
+    max_lag: Option<usize>,
+
+    /// Print the vibrational density of states instead of the VACF
+    #[arg(long)]
+    dos: bool,
+
+    /// Number of frequency points of the density of states
+    #[arg(short, long, default_value_t = 500)]
+    n_freq: usize,
+
+    /// Maximum frequency of the density of states (THz), Nyquist by default
+    #[arg(short = 'f', long)]
+    max_freq: Option<f64>,
+}
+
+/// Per lag sums of `v(t0) * v(t0 + t)` and their counts
+type Correlation = Vec<(usize, f64)>;
+
+fn get_atom_vacf(velocities: &[[f64; 3]], max_lag: usize, origin_stride: usize) -> Correlation {
+    let n = velocities.len();
+    (0..max_lag.min(n))
+        .map(|lag| {
+            (0..n - lag)
+                .step_by(origin_stride)
+                .map(|t0| (velocities[t0], velocities[t0 + lag]))
+                .filter(|(a, b)| !a[0].is_nan() && !b[0].is_nan())
+                .fold((0, 0.0), |(count, sum), (a, b)| {
+                    let dot = iter::zip(a, b).map(|(a, b)| a * b).sum::<f64>();
+                    (count + 1, sum + dot)
+                })
+        })
+        .collect()
+}
+
+fn merge_correlations(
+    mut a: BTreeMap<usize, Correlation>,
+    b: BTreeMap<usize, Correlation>,
+) -> BTreeMap<usize, Correlation> {
+    for (atype, correlation) in b {
+        let sums = a
+            .entry(atype)
+            .or_insert_with(|| vec![(0, 0.0); correlation.len()]);
+        iter::zip(sums, correlation).for_each(|(a, b)| {
+            a.0 += b.0;
+            a.1 += b.1;
+        });
+    }
+    a
+}
+
+fn get_vacf(
+    trajectory: &Trajectory,
+    max_lag: usize,
+    origin_stride: usize,
+) -> BTreeMap<usize, Vec<f64>> {
+    (0..trajectory.atoms_count())
+        .into_par_iter()
+        .fold(BTreeMap::new, |correlations, atom_i| {
+            let Some(atype) = trajectory
+                .get_series_by_index("type", atom_i)
+                .iter()
+                .find(|t| !t.is_nan())
+            else {
+                return correlations;
+            };
+            let [vx, vy, vz] =
+                ["vx", "vy", "vz"].map(|key| trajectory.get_series_by_index(key, atom_i));
+            let velocities = (0..trajectory.steps_count())
+                .map(|i| [vx[i], vy[i], vz[i]])
+                .collect::<Vec<_>>();
+            let correlation = get_atom_vacf(&velocities, max_lag, origin_stride);
+            merge_correlations(
+                correlations,
+                BTreeMap::from([(*atype as usize, correlation)]),
+            )
+        })
+        .reduce(BTreeMap::new, merge_correlations)
+        .into_iter()
+        .map(|(atype, correlation)| {
+            let vacf = correlation
+                .into_iter()
+                .map(|(count, sum)| {
+                    if count > 0 {
+                        sum / count as f64
+                    } else {
+                        f64::NAN
+                    }
+                })
+                .collect();
+            (atype, vacf)
+        })
+        .collect()
+}
+
+/// Cosine transform of the normalized VACF with a Hann window, normalized
+/// to unit area over the frequency range. Lags without any origin are skipped.
+fn get_dos(vacf: &[f64], times: &[f64], freqs: &[f64]) -> Vec<f64> {
+    let n = vacf.len();
+    let c0 = vacf[0];
+    let weights = (0..n)
+        .map(|k| {
+            let window = 0.5 * (1.0 + (PI * k as f64 / (n.max(2) - 1) as f64).cos());
+            let dt = match k {
+                0 => times.get(1).map_or(0.0, |t| t - times[0]) / 2.0,
+                k if k == n - 1 => (times[k] - times[k - 1]) / 2.0,
+                k => (times[k + 1] - times[k - 1]) / 2.0,
+            };
+            if vacf[k].is_nan() {
+                0.0
+            } else {
+                window * dt * vacf[k] / c0
+            }
+        })
+        .collect::<Vec<_>>();
+    let dos = freqs
+        .iter()
+        .map(|freq| {
+            iter::zip(&weights, times)
+                .map(|(w, t)| w * (2.0 * PI * freq * t).cos())
+                .sum::<f64>()
+        })
+        .collect::<Vec<_>>();
+    let d_freq = freqs.get(1).map_or(1.0, |f| f - freqs[0]);
+    let area = dos.iter().sum::<f64>() * d_freq;
+    dos.into_iter().map(|g| g / area).collect()
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    let dump = DumpFile::read(&cli.dump_file, &[])?;
+    let trajectory = Trajectory::new(dump.get_snapshots(), &["type", "vx", "vy", "vz"]);
+    info!(
+        "trajectory: {} atoms, {} steps",
+        trajectory.atoms_count(),
+        trajectory.steps_count()
+    );
+    if trajectory.steps_count() < 2 {
+        bail!("At least two snapshots are required");
+    }
+    let max_lag = cli.max_lag.unwrap_or(trajectory.steps_count());
+    if max_lag < 2 {
+        bail!("The maximum lag must be at least 2 frames");
+    }
+    let vacf = get_vacf(&trajectory, max_lag, cli.origin_stride.max(1));
+    let steps = trajectory.steps();
+    let times = steps
+        .iter()
+        .take(max_lag)
+        .map(|step| (step - steps[0]) as f64 * cli.dt)
+        .collect::<Vec<_>>();
+    let types = vacf.keys().map(|t| t.to_string()).collect::<Vec<_>>();
+    if cli.dos {
+        let max_freq = cli.max_freq.unwrap_or(0.5 / times[1]);
+        let freqs = (0..cli.n_freq)
+            .map(|i| i as f64 * max_freq / cli.n_freq as f64)
+            .collect::<Vec<_>>();
+        let dos = vacf
+            .values()
+            .map(|vacf| get_dos(vacf, &times, &freqs))
+            .collect::<Vec<_>>();
+        let table = freqs
+            .iter()
+            .enumerate()
+            .map(|(i, freq)| {
+                iter::once(*freq)
+                    .chain(dos.iter().map(|dos| dos[i]))
+                    .map(|x| format!("{x:10.4}"))
+                    .join("\t")
+            })
+            .join("\n");
+        println!("# freq(THz) dos(type {})\n{table}", types.join(" "));
+    } else {
+        let table = times
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| vacf.values().any(|vacf| !vacf[*i].is_nan()))
+            .map(|(i, time)| {
+                iter::once(*time)
+                    .chain(vacf.values().flat_map(|vacf| [vacf[i], vacf[i] / vacf[0]]))
+                    .map(|x| format!("{x:10.4}"))
+                    .join("\t")
+            })
+            .join("\n");
+        let header = types.iter().map(|t| format!("vacf_{t} norm_{t}")).join(" ");
+        println!("# time(ps) {header}\n{table}");
+    }
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use clap::Parser;
+use itertools::Itertools;
+use lammps_util_rust::{DumpFile, IteratorAvg, voronoi_snapshot};
+use log::info;
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Per-atom Voronoi volumes and face counts
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    dump_file: PathBuf,
+
+    /// Neighbour cutoff (A), cells reaching cutoff / 2√3 from the atom are open
+    #[arg(short, long, default_value_t = 10.0)]
+    cutoff: f64,
+
+    /// Faces smaller than this are not counted (A^2)
+    #[arg(short, long, default_value_t = 0.0)]
+    min_face_area: f64,
+
+    #[arg(short, long)]
+    timestep: Option<u64>,
+
+    /// Dump file to write with the Voronoi columns added
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    let timesteps = cli.timestep.map(|t| vec![t]).unwrap_or_default();
+    let dump = DumpFile::read(&cli.dump_file, &timesteps)?;
+    let snapshot = *dump.get_snapshots().first().context("No snapshots")?;
+    info!("step {}: {} atoms", snapshot.step, snapshot.atoms_count);
+    let snapshot = voronoi_snapshot(snapshot, cli.cutoff, cli.min_face_area);
+    let types = snapshot.get_property("type");
+    let volume = snapshot.get_property("voronoi_volume");
+    let faces = snapshot.get_property("voronoi_faces");
+    let open = snapshot.get_property("voronoi_open");
+    let mut groups = BTreeMap::<usize, (usize, Vec<usize>)>::new();
+    for i in 0..snapshot.atoms_count {
+        let (open_count, closed) = groups.entry(types[i] as usize).or_default();
+        if open[i] == 0.0 {
+            closed.push(i);
+        } else {
+            *open_count += 1;
+        }
+    }
+    let table = groups
+        .iter()
+        .map(|(atype, (open_count, closed))| {
+            let (volume, volume_std) = closed
+                .iter()
+                .map(|&i| volume[i])
+                .avg_with_std()
+                .unwrap_or_default();
+            let (faces, faces_std) = closed
+                .iter()
+                .map(|&i| faces[i])
+                .avg_with_std()
+                .unwrap_or_default();
+            let density = if volume > 0.0 { 1.0 / volume } else { 0.0 };
+            [
+                atype.to_string(),
+                closed.len().to_string(),
+                open_count.to_string(),
+            ]
+            .into_iter()
+            .chain(
+                [volume, volume_std, density, faces, faces_std]
+                    .iter()
+                    .map(|x| format!("{x:10.4}")),
+            )
+            .join("\t")
+        })
+        .join("\n");
+    println!("# type closed open volume σ(volume) density faces σ(faces)\n{table}");
+    if let Some(output) = &cli.output {
+        DumpFile::new(vec![snapshot]).save(output)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use anyhow::{Result, bail};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use itertools::Itertools;
+use lammps_util_rust::{
+    DumpFile, DumpSnapshot, WignerSeitz, copy_snapshot_with_indices, process_results_dir,
+};
+use log::debug;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// Wigner-Seitz defect analysis of the final state against the initial lattice
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+
+    /// Final dump file name inside a run dir
+    #[arg(short, long, default_value = "dump.final")]
+    dump_final: String,
+
+    /// Print the distribution of the defects instead of their counts
+    #[arg(short = 'D', long)]
+    distribution: Option<Distribution>,
+
+    /// Bin width of the distribution (A)
+    #[arg(short = 'w', long, default_value_t = 2.0)]
+    bin_width: f64,
+
+    /// X of the crater axis
+    #[arg(long, default_value_t = 0.0)]
+    center_x: f64,
+
+    /// Y of the crater axis
+    #[arg(long, default_value_t = 0.0)]
+    center_y: f64,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Defects for a single run dir
+    Single(SingleCMD),
+
+    /// Defects for the whole results folder
+    Multi(MultiCMD),
+}
+
+#[derive(Args)]
+struct SingleCMD {
+    run_dir: PathBuf,
+}
+
+#[derive(Args)]
+struct MultiCMD {
+    results_dir: PathBuf,
+
+    /// Number of threads to run in parallel
+    #[arg(short, long, default_value_t = 2)]
+    threads: usize,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Distribution {
+    /// Depth below the initial surface
+    Depth,
+    /// Distance from the crater axis
+    Radial,
+}
+
+const VACANCY: usize = 0;
+const INTERSTITIAL: usize = 1;
+const ANTISITE: usize = 2;
+
+/// Defects of a run, vacancies are positioned at their reference sites
+struct Defects {
+    /// Vacancies, interstitials and antisites by type
+    counts: BTreeMap<usize, [usize; 3]>,
+    /// Kind and position of every defect
+    positions: Vec<(usize, [f64; 3])>,
+    zero_lvl: f64,
+}
+
+fn get_position(snapshot: &DumpSnapshot, i: usize) -> [f64; 3] {
+    ["x", "y", "z"].map(|key| snapshot.get_property(key)[i])
+}
+
+fn analyze_single_run(dir: &Path, cli: &Cli) -> Result<Defects> {
+    let dump_initial = DumpFile::read(&dir.join("dump.initial"), &[])?;
+    let snapshot_initial = dump_initial.get_snapshots()[0];
+    let dump_final = DumpFile::read(&dir.join(&cli.dump_final), &[])?;
+    let snapshot_final = dump_final.get_snapshots()[0];
+    if snapshot_initial.atoms_count == 0 || snapshot_final.atoms_count == 0 {
+        bail!("{}: empty initial or final snapshot", dir.display());
+    }
+    let ws = WignerSeitz::new(snapshot_initial, snapshot_final);
+    let vacancies = ws.get_vacancies();
+    let interstitials = ws.get_interstitials();
+    let antisites = ws.get_antisites();
+    debug!(
+        "{}: {} vacancies, {} interstitials, {} antisites",
+        dir.display(),
+        vacancies.len(),
+        interstitials.len(),
+        antisites.len()
+    );
+    let mut defects = Defects {
+        counts: BTreeMap::new(),
+        positions: Vec::new(),
+        zero_lvl: snapshot_initial.get_zero_lvl(),
+    };
+    for &site_i in &vacancies {
+        defects.counts.entry(ws.get_site_type(site_i)).or_default()[VACANCY] += 1;
+        let position = get_position(snapshot_initial, site_i);
+        defects.positions.push((VACANCY, position));
+    }
+    for (kind, atoms) in [(INTERSTITIAL, &interstitials), (ANTISITE, &antisites)] {
+        for &atom_i in atoms {
+            defects.counts.entry(ws.get_atom_type(atom_i)).or_default()[kind] += 1;
+            defects
+                .positions
+                .push((kind, get_position(snapshot_final, atom_i)));
+        }
+    }
+    for (name, snapshot, indices) in [
+        ("dump.vacancies", snapshot_initial, vacancies),
+        ("dump.interstitials", snapshot_final, interstitials),
+        ("dump.antisites", snapshot_final, antisites),
+    ] {
+        let snapshot = copy_snapshot_with_indices(snapshot, indices.into_iter());
+        DumpFile::new(vec![snapshot]).save(&dir.join(name))?;
+    }
+    Ok(defects)
+}
+
+fn get_counts_table(runs: &[(Option<usize>, Defects)]) -> String {
+    let run_header = if runs.iter().any(|(num, _)| num.is_some()) {
+        "run "
+    } else {
+        ""
+    };
+    let table = runs
+        .iter()
+        .flat_map(|(num, defects)| {
+            defects.counts.iter().map(move |(atype, counts)| {
+                num.iter()
+                    .map(ToString::to_string)
+                    .chain([atype.to_string()])
+                    .chain(counts.iter().map(ToString::to_string))
+                    .join("\t")
+            })
+        })
+        .join("\n");
+    format!("# {run_header}type vacancies interstitials antisites\n{table}")
+}
+
+fn get_distribution_table(
+    runs: &[(Option<usize>, Defects)],
+    distribution: Distribution,
+    cli: &Cli,
+) -> String {
+    let mut histogram = BTreeMap::<i64, [usize; 3]>::new();
+    for (_, defects) in runs {
+        for (kind, [x, y, z]) in &defects.positions {
+            let value = match distribution {
+                Distribution::Depth => defects.zero_lvl - z,
+                Distribution::Radial => (x - cli.center_x).hypot(y - cli.center_y),
+            };
+            let bin = (value / cli.bin_width).floor() as i64;
+            histogram.entry(bin).or_default()[*kind] += 1;
+        }
+    }
+    let runs_count = runs.len().max(1) as f64;
+    let table = histogram
+        .into_iter()
+        .map(|(bin, counts)| {
+            let center = (bin as f64 + 0.5) * cli.bin_width;
+            std::iter::once(center)
+                .chain(counts.map(|count| count as f64 / runs_count))
+                .map(|x| format!("{x:10.4}"))
+                .join("\t")
+        })
+        .join("\n");
+    let name = match distribution {
+        Distribution::Depth => "depth",
+        Distribution::Radial => "radius",
+    };
+    format!("# {name} vacancies interstitials antisites\n{table}")
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let cli = Cli::parse();
+    if cli.bin_width.is_nan() || cli.bin_width <= 0.0 {
+        bail!("Bin width must be positive, got {}", cli.bin_width);
+    }
+    let runs = match &cli.command {
+        Commands::Single(args) => vec![(None, analyze_single_run(&args.run_dir, &cli)?)],
+        Commands::Multi(args) => process_results_dir(&args.results_dir, args.threads, |dir| {
+            analyze_single_run(&dir.path, &cli)
+        })?
+        .into_iter()
+        .map(|(dir, defects)| (Some(dir.num), defects))
+        .collect(),
+    };
+    let table = match cli.distribution {
+        Some(distribution) => get_distribution_table(&runs, distribution, &cli),
+        None => get_counts_table(&runs),
+    };
+    println!("{table}");
+    Ok(())
+}